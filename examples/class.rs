@@ -1,6 +1,9 @@
 extern crate parchive;
 
-use parchive::{tagged_enum, Archivable, Archive, ArchiveReader, ArchiveWriter, LenVec, Result};
+use parchive::{
+    tagged_enum, Archivable, Archive, ArchiveReader, ArchiveUpdater, ArchiveWriter, Biased, Endian,
+    LenVec, Magic, MagicBytes, Result,
+};
 
 tagged_enum! {
     enum CpInfo : u8 {
@@ -30,9 +33,16 @@ impl CpInfo {
     }
 }
 
+struct ClassMagic;
+
+impl MagicBytes for ClassMagic {
+    const BYTES: &'static [u8] = &[0xca, 0xfe, 0xba, 0xbe];
+}
+
 #[derive(Default, Debug)]
 struct ClassFile {
-    magic: u32,
+    // checked to be `0xCAFEBABE` when read
+    magic: Magic<ClassMagic>,
     minor_version: u16,
     major_version: u16,
     // we will not store the length directly
@@ -46,44 +56,28 @@ struct ClassFile {
 
 impl Archivable for ClassFile {
     fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
-        // Class Files are in big-endian
-        ar.set_little_endian(false);
-        ar.archive(&mut self.magic)?;
-        ar.archive(&mut self.minor_version)?;
-        ar.archive(&mut self.major_version)?;
+        // Class Files are in big-endian, restore the caller's setting afterwards
+        ar.with_endian(Endian::Big, |ar| {
+            ar.archive(&mut self.magic)?;
+            ar.archive(&mut self.minor_version)?;
+            ar.archive(&mut self.major_version)?;
 
-        if Ar::IS_READING {
-            let mut cp_count: u16 = 0;
-            ar.archive(&mut cp_count)?;
+            // the count is one more than the number of entries, and Long and Double
+            // entries count twice
+            ar.archive_weighted_vec::<Biased<u16, 1>, _>(&mut self.constant_pool, |x| {
+                if x.is_double() {
+                    2
+                } else {
+                    1
+                }
+            })?;
 
-            let mut i = 1;
-            while i < cp_count {
-                let mut v: CpInfo = Default::default();
-                ar.archive(&mut v)?;
-                i += if v.is_double() { 2 } else { 1 };
-                self.constant_pool.push(v);
-            }
-        } else {
-            let mut cp_count: u16 = self
-                .constant_pool
-                .iter()
-                .map(|x| -> u16 {
-                    if x.is_double() {
-                        2
-                    } else {
-                        1
-                    }
-                })
-                .sum();
-            ar.archive(&mut cp_count)?;
-            ar.archive_vec(self.constant_pool.len(), &mut self.constant_pool)?;
-        }
-
-        ar.archive(&mut self.access_flags)?;
-        ar.archive(&mut self.this_class)?;
-        ar.archive(&mut self.super_class)?;
-        ar.archive(&mut self.interfaces)?;
-        Ok(())
+            ar.archive(&mut self.access_flags)?;
+            ar.archive(&mut self.this_class)?;
+            ar.archive(&mut self.super_class)?;
+            ar.archive(&mut self.interfaces)?;
+            Ok(())
+        })
     }
 }
 
@@ -99,4 +93,14 @@ pub fn main() {
     let file2 = std::fs::File::create("./test/Object2.class").unwrap();
     let mut ar2 = ArchiveWriter::new(file2);
     ar2.archive(&mut cf).unwrap();
+
+    // patch the copy in place, only the version bytes are rewritten
+    let file3 = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("./test/Object2.class")
+        .unwrap();
+    let mut up = ArchiveUpdater::new(file3);
+    up.update(|cf: &mut ClassFile| cf.major_version = 52)
+        .unwrap();
 }
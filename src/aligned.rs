@@ -0,0 +1,81 @@
+use crate::Archivable;
+use crate::{Archive, Result};
+
+/// A struct representing a `T` that always starts at an offset that is a multiple of
+/// `N`. Zero padding is inserted before the value as needed. It `Deref`s to `T`.
+#[derive(Default, PartialEq, Eq)]
+pub struct Aligned<T, const N: usize>
+where
+    T: Archivable,
+{
+    value: T,
+}
+
+impl<T, const N: usize> Aligned<T, N>
+where
+    T: Archivable,
+{
+    pub fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, const N: usize> Archivable for Aligned<T, N>
+where
+    T: Archivable,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.align(N)?;
+        ar.archive(&mut self.value)
+    }
+}
+
+impl<T, const N: usize> std::ops::Deref for Aligned<T, N>
+where
+    T: Archivable,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, const N: usize> std::ops::DerefMut for Aligned<T, N>
+where
+    T: Archivable,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T, const N: usize> std::fmt::Debug for Aligned<T, N>
+where
+    T: Archivable + std::fmt::Debug,
+{
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(fmt)
+    }
+}
+
+/// A struct representing `N` reserved bytes, which are skipped when read and written
+/// as the fill byte (see `Archive::skip`).
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Reserved<const N: usize>;
+
+impl<const N: usize> Archivable for Reserved<N> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.skip(N as u64)
+    }
+}
+
+impl<const N: usize> std::fmt::Debug for Reserved<N> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "Reserved<{}>", N)
+    }
+}
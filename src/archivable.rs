@@ -1,10 +1,35 @@
-use crate::Error;
 use crate::{Archive, Result};
+use crate::{Error, PointerWidth, Terminated};
+use std::marker::PhantomData;
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+};
 
 /// A trait representing an archivable object - one that can be both written to
 /// and read from binary files. Implemented for most primitive types.
 pub trait Archivable: Default {
     fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()>;
+
+    /// Archives all of `values` in order. Primitive types override this to transfer
+    /// whole runs of elements at once.
+    #[doc(hidden)]
+    fn archive_all<Ar: Archive>(values: &mut [Self], ar: &mut Ar) -> Result<()> {
+        for v in values.iter_mut() {
+            ar.archive(v)?;
+        }
+        Ok(())
+    }
+}
+
+/// Views a slice of primitives as the bytes it occupies in memory.
+///
+/// # Safety
+///
+/// `T` must be a primitive integer or float type, for which every byte pattern is a
+/// valid value and which has no padding.
+unsafe fn as_bytes_mut<T>(values: &mut [T]) -> &mut [u8] {
+    std::slice::from_raw_parts_mut(values.as_mut_ptr().cast(), std::mem::size_of_val(values))
 }
 
 macro_rules! prim_archivable {
@@ -36,10 +61,101 @@ macro_rules! prim_archivable {
                 }
                 Ok(())
             }
+
+            fn archive_all<Ar: Archive>(values: &mut [Self], ar: &mut Ar) -> Result<()> {
+                // values are transferred in their memory, swapped in place when the
+                // archive endianness differs from the host one
+                let swap = std::mem::size_of::<$typ>() > 1
+                    && ar.is_little_endian() != cfg!(target_endian = "little");
+                let swap_all = |values: &mut [Self]| {
+                    for v in values.iter_mut() {
+                        let mut b = v.to_ne_bytes();
+                        b.reverse();
+                        *v = <$typ>::from_ne_bytes(b);
+                    }
+                };
+                if Ar::IS_READING {
+                    // SAFETY: `$typ` is a primitive integer or float
+                    ar.read_exact(unsafe { as_bytes_mut(values) })?;
+                    if swap {
+                        swap_all(values);
+                    }
+                    Ok(())
+                } else {
+                    if swap {
+                        swap_all(values);
+                    }
+                    // SAFETY: as above
+                    let result = ar.write_all(unsafe { as_bytes_mut(values) });
+                    if swap {
+                        swap_all(values);
+                    }
+                    result
+                }
+            }
         }
     };
 }
 
+/// Archives `usize`/`isize` through the fixed-size type matching the archive
+/// pointer width, so the encoding does not depend on the host.
+macro_rules! pointer_archivable {
+    ($typ: ty, $t32: ty, $t64: ty) => {
+        impl Archivable for $typ {
+            fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+                match ar.pointer_width() {
+                    PointerWidth::Bits32 => {
+                        // the current value only matters when writing
+                        let mut v: $t32 = if Ar::IS_READING {
+                            0
+                        } else {
+                            <$t32>::try_from(*self).map_err(|_| {
+                                Error::ValueError(format!("{} does not fit in 32 bits", self))
+                            })?
+                        };
+                        ar.archive(&mut v)?;
+                        *self = <$typ>::try_from(v).map_err(|_| {
+                            Error::ValueError(format!("{} does not fit in {}", v, stringify!($typ)))
+                        })?;
+                    }
+                    PointerWidth::Bits64 => {
+                        // the current value only matters when writing
+                        let mut v: $t64 = if Ar::IS_READING {
+                            0
+                        } else {
+                            <$t64>::try_from(*self).map_err(|_| {
+                                Error::ValueError(format!("{} does not fit in 64 bits", self))
+                            })?
+                        };
+                        ar.archive(&mut v)?;
+                        *self = <$typ>::try_from(v).map_err(|_| {
+                            Error::ValueError(format!("{} does not fit in {}", v, stringify!($typ)))
+                        })?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Archives `Option<NonZero*>` as the underlying integer, with `0` standing for `None`.
+/// The `NonZero*` types themselves have no `Default`, so they cannot be `Archivable`.
+macro_rules! nonzero_archivable {
+    ($($typ: ty => $prim: ty),+) => {
+        $(
+            impl Archivable for Option<$typ> {
+                fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+                    let mut v = self.map_or(0, <$typ>::get);
+                    ar.archive(&mut v)?;
+                    *self = <$typ>::new(v);
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
 macro_rules! tuple_archivable {
     ($($tfs:ident $vs:ident),+) => {
         impl< $($tfs : Archivable),+ > Archivable for ( $($tfs),+ ) {
@@ -57,7 +173,15 @@ macro_rules! tuple_archivable {
 prim_archivable!(u8, u16, u32, u64, u128);
 prim_archivable!(i8, i16, i32, i64, i128);
 prim_archivable!(f32, f64);
-prim_archivable!(usize, isize);
+
+pointer_archivable!(usize, u32, u64);
+pointer_archivable!(isize, i32, i64);
+
+nonzero_archivable!(
+    NonZeroU8 => u8, NonZeroU16 => u16, NonZeroU32 => u32, NonZeroU64 => u64, NonZeroU128 => u128,
+    NonZeroI8 => i8, NonZeroI16 => i16, NonZeroI32 => i32, NonZeroI64 => i64, NonZeroI128 => i128,
+    NonZeroUsize => usize, NonZeroIsize => isize
+);
 
 tuple_archivable!(T0 v0, T1 v1);
 tuple_archivable!(T0 v0, T1 v1, T2 v2);
@@ -69,24 +193,73 @@ tuple_archivable!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7);
 tuple_archivable!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7, T8 v8);
 tuple_archivable!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7, T8 v8, T9 v9);
 
+/// Archived as a single byte, `0` or `1`. Any other value is rejected when read.
+impl Archivable for bool {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let mut v = *self as u8;
+        ar.archive(&mut v)?;
+        *self = match v {
+            0 => false,
+            1 => true,
+            v => return Err(Error::ValueError(format!("invalid bool value {}", v))),
+        };
+        Ok(())
+    }
+}
+
+/// Archived as a `u32` code point, which is validated when read.
+impl Archivable for char {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let mut v = *self as u32;
+        ar.archive(&mut v)?;
+        *self = char::from_u32(v)
+            .ok_or_else(|| Error::ValueError(format!("invalid char value {:#x}", v)))?;
+        Ok(())
+    }
+}
+
+/// Archived as its `N` elements, without a length prefix. Only implemented up to
+/// `N = 32`, as std only provides `Default` for those arrays.
+impl<T, const N: usize> Archivable for [T; N]
+where
+    T: Archivable,
+    [T; N]: Default,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive_slice(N, self)
+    }
+}
+
+impl<T: Archivable> Archivable for Box<T> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive(&mut **self)
+    }
+}
+
+impl<T: Archivable> Archivable for Wrapping<T> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive(&mut self.0)
+    }
+}
+
+/// Archived as nothing.
+impl<T> Archivable for PhantomData<T> {
+    fn archive<Ar: Archive>(&mut self, _: &mut Ar) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Archived as null-terminated UTF-8.
 impl Archivable for String {
     fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
-        if Ar::IS_READING {
-            let mut v = vec![];
-            let mut buf = [0 as u8];
-            loop {
-                ar.read_exact(&mut buf)?;
-                if buf[0] == 0 {
-                    *self = String::from_utf8(v).or_else(|e| {
-                        Err(Error::ValueError(format!("invalid string value {}", e)))
-                    })?;
-                    return Ok(());
-                } else {
-                    v.push(buf[0]);
-                }
-            }
+        let mut bytes = Terminated::<u8>::new(if Ar::IS_READING {
+            vec![]
         } else {
-            Ok(())
-        }
+            self.clone().into_bytes()
+        });
+        ar.archive(&mut bytes)?;
+        *self = String::from_utf8(bytes.into())
+            .map_err(|e| Error::ValueError(format!("invalid string value {}", e)))?;
+        Ok(())
     }
 }
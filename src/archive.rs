@@ -1,9 +1,38 @@
-use crate::{Archivable, Result};
+use std::any::Any;
+use std::io::{ErrorKind, SeekFrom};
+
+use crate::checksum::Tap;
+use crate::context::Contexts;
+use crate::{
+    Archivable, ArchiveReader, ArchiveWriter, Checksum, Endian, Error, Lazy, LenPolicy,
+    PresenceFields, Result, Transform,
+};
+
+/// Width used to archive `usize` and `isize` values, independent of the host.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PointerWidth {
+    Bits32,
+    #[default]
+    Bits64,
+}
 
 /// Internal read/write methods
 pub trait ArchiveInternal {
     fn write_all(&mut self, value: &[u8]) -> Result<()>;
     fn read_exact(&mut self, value: &mut [u8]) -> Result<()>;
+    fn skip_bytes(&mut self, length: u64) -> Result<()>;
+    fn contexts(&self) -> &Contexts;
+    fn contexts_mut(&mut self) -> &mut Contexts;
+    /// Checksums fed with every byte read or written, see `Archive::checksummed`.
+    fn taps_mut(&mut self) -> &mut Vec<Box<dyn Tap>>;
+    /// Starts a transformed region: position and limit restart from its decoded
+    /// bytes, which reads are served from and writes are collected into.
+    fn push_layer(&mut self, data: Vec<u8>);
+    /// Ends the innermost transformed region, returning its decoded bytes.
+    fn pop_layer(&mut self) -> Vec<u8>;
+    /// Notes that the bytes from `start` to `end` were read without being decoded,
+    /// i.e. skipped or padding, see `ArchiveUpdater`.
+    fn mark_skipped(&mut self, start: u64, end: u64);
 }
 
 /// A trait representing an Archive.
@@ -15,6 +44,171 @@ pub trait Archive: ArchiveInternal + Sized {
     fn set_little_endian(&mut self, little_endian: bool);
     fn is_little_endian(&self) -> bool;
 
+    fn set_endian(&mut self, endian: Endian) {
+        self.set_little_endian(endian.is_little());
+    }
+
+    fn endian(&self) -> Endian {
+        if self.is_little_endian() {
+            Endian::Little
+        } else {
+            Endian::Big
+        }
+    }
+
+    /// Runs `f` with the archive set to `endian`, restoring the previous endianness
+    /// afterwards, even if `f` fails.
+    fn with_endian<T>(
+        &mut self,
+        endian: Endian,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let previous = self.endian();
+        self.set_endian(endian);
+        let result = f(self);
+        self.set_endian(previous);
+        result
+    }
+
+    /// Sets the offset where the data ends. Reading or writing past it fails as if the
+    /// stream ended there. See `with_limit`.
+    fn set_limit(&mut self, limit: Option<u64>);
+    fn limit(&self) -> Option<u64>;
+
+    /// Runs `f` as a sub-archive of `length` bytes starting at the current position,
+    /// restoring the previous limit afterwards. `f` may use less than `length` bytes.
+    fn with_limit<T>(&mut self, length: u64, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let previous = self.limit();
+        let limit = self.position() + length;
+        self.set_limit(Some(previous.map_or(limit, |p| p.min(limit))));
+        let result = f(self);
+        self.set_limit(previous);
+        result
+    }
+
+    /// Runs `f` on a region protected by a checksum of type `C`, stored right after it.
+    /// When writing, the checksum of the bytes written by `f` is appended. When
+    /// reading, it is read and compared, failing with `Error::ChecksumMismatch`.
+    /// Bytes skipped by seeking are not covered.
+    fn checksummed<C: Checksum, T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.taps_mut().push(Box::new(C::default()));
+        let result = f(self);
+        let tap = self.taps_mut().pop().unwrap();
+        let result = result?;
+
+        let computed = tap.into_any().downcast::<C>().unwrap().value();
+        let offset = self.position();
+        let mut stored = computed;
+        self.archive(&mut stored)?;
+        if stored != computed {
+            return Err(Error::ChecksumMismatch {
+                stored: stored.into(),
+                computed: computed.into(),
+                offset,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Runs `f` on a region stored through `transform`, such as an encryption or a
+    /// compression. When writing, the bytes written by `f` are encoded with it at the
+    /// end of the region. When reading, the region extends to the current limit or the
+    /// end of the data, so regions of known length must be wrapped in `with_limit`.
+    /// Inside the region, the position counts decoded bytes from its start, and seeking
+    /// is not supported.
+    fn transformed<T>(
+        &mut self,
+        mut transform: impl Transform,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        if Self::IS_READING {
+            let mut stored = Vec::new();
+            let mut chunk = [0u8; 4096];
+            loop {
+                let start = self.position();
+                match self.read_exact(&mut chunk) {
+                    Ok(()) => stored.extend_from_slice(&chunk),
+                    Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                        let read = (self.position() - start) as usize;
+                        stored.extend_from_slice(&chunk[..read]);
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            self.push_layer(transform.decode(stored)?);
+            let result = f(self);
+            self.pop_layer();
+            result
+        } else {
+            self.push_layer(Vec::new());
+            let result = f(self);
+            let data = self.pop_layer();
+            let result = result?;
+            self.write_all(&transform.encode(data)?)?;
+            Ok(result)
+        }
+    }
+
+    /// Sets the format version, usually from a header field, so that nested
+    /// `Archivable`s can depend on it (see `since`). Starts at `0`. When writing, it
+    /// selects which version of the format is emitted.
+    fn set_version(&mut self, version: u32);
+    fn version(&self) -> u32;
+
+    /// Runs `f` only if the format version is at least `version`. Returns whether it ran.
+    fn since(&mut self, version: u32, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<bool> {
+        if self.version() >= version {
+            f(self)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Archives a field added in `version`. For older versions nothing is written,
+    /// and when reading the field is reset to its default.
+    fn archive_since<T: Archivable>(&mut self, version: u32, value: &mut T) -> Result<()> {
+        if !self.since(version, |ar| ar.archive(value))? && Self::IS_READING {
+            *value = T::default();
+        }
+        Ok(())
+    }
+
+    /// Attaches a user context of type `T`, replacing and returning any previous one.
+    /// Nested `Archivable`s can then reach it through `context` and `context_mut`,
+    /// e.g. to resolve indices into a previously read table.
+    fn set_context<T: Any>(&mut self, value: T) -> Option<T> {
+        self.contexts_mut().insert(value)
+    }
+
+    fn context<T: Any>(&self) -> Option<&T> {
+        self.contexts().get()
+    }
+
+    fn context_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.contexts_mut().get_mut()
+    }
+
+    /// Detaches and returns the user context of type `T`.
+    fn take_context<T: Any>(&mut self) -> Option<T> {
+        self.contexts_mut().remove()
+    }
+
+    /// Sets the width used for `usize` and `isize`. Defaults to `PointerWidth::Bits64`.
+    fn set_pointer_width(&mut self, width: PointerWidth);
+    fn pointer_width(&self) -> PointerWidth;
+
+    /// The current byte offset, counted from where the underlying stream was when
+    /// the archive was created. Starts at `0`, advances with every byte read or
+    /// written, and follows every `seek`.
+    fn position(&self) -> u64;
+
+    /// When set, reading padding (see `pad` and `align`) fails if the padding bytes
+    /// do not match the fill byte. Off by default.
+    fn set_strict_padding(&mut self, strict: bool);
+    fn is_strict_padding(&self) -> bool;
+
     fn archive_option<T: Archivable>(
         &mut self,
         is_present: bool,
@@ -37,46 +231,338 @@ pub trait Archive: ArchiveInternal + Sized {
         Ok(())
     }
 
+    /// Archives a flags integer followed by the optional fields whose bit is set in
+    /// it, in order. When writing, the bits of `fields` are first updated in `flags`
+    /// from which options are `Some`. Other bits of `flags` are archived unchanged.
+    /// Fails if a bit index does not fit in `F`, or is used by several fields.
+    ///
+    /// ```ignore
+    /// ar.archive_presence(&mut self.flags, ((0, &mut self.name), (3, &mut self.size)))?;
+    /// ```
+    fn archive_presence<F, P>(&mut self, flags: &mut F, mut fields: P) -> Result<()>
+    where
+        F: Archivable + Copy + Into<u64> + TryFrom<u64>,
+        P: PresenceFields,
+    {
+        let width = std::mem::size_of::<F>() as u32 * 8;
+        if fields.max_bit() >= width.min(u64::BITS) {
+            return Err(Error::ValueError(format!(
+                "presence bit {} does not fit in {}-bit flags",
+                fields.max_bit(),
+                width
+            )));
+        }
+        if fields.mask().count_ones() != P::COUNT {
+            return Err(Error::ValueError(format!(
+                "presence bits {:#x} are shared by several fields",
+                fields.mask()
+            )));
+        }
+        if !Self::IS_READING {
+            let bits = ((*flags).into() & !fields.mask()) | fields.present();
+            *flags = F::try_from(bits).map_err(|_| {
+                Error::ValueError(format!("presence bits {:#x} do not fit in flags", bits))
+            })?;
+        }
+        self.archive(flags)?;
+        fields.archive_fields(self, (*flags).into())
+    }
+
     fn archive_vec<T: Archivable>(&mut self, length: usize, value: &mut Vec<T>) -> Result<()> {
         if Self::IS_READING {
-            *value = Vec::with_capacity(length);
-            for _ in 0..length {
-                let mut um = T::default();
-                self.archive(&mut um)?;
-                value.push(um);
+            value.clear();
+            // grow in doubling steps, so a bogus length fails when the data ends
+            // instead of allocating everything up front, in few reads
+            let mut step = 4096;
+            while value.len() < length {
+                let start = value.len();
+                value.resize_with(length.min(start + step), T::default);
+                T::archive_all(&mut value[start..], self)?;
+                step = step.saturating_mul(2);
             }
             Ok(())
         } else {
             assert_eq!(length, value.len());
-            for v in value.iter_mut() {
-                self.archive(v)?;
+            T::archive_all(value, self)
+        }
+    }
+
+    /// Archives a vector prefixed with its length, encoded according to `L`.
+    fn archive_len_vec<L: LenPolicy, T: Archivable>(&mut self, value: &mut Vec<T>) -> Result<()> {
+        let unit = match L::UNIT_BYTES {
+            None => {
+                let len = L::archive_len(self, value.len())?;
+                return self.archive_vec(len, value);
             }
-            Ok(())
+            Some(unit) => unit,
+        };
+
+        if Self::IS_READING {
+            let size = L::archive_len(self, 0)?
+                .checked_mul(unit)
+                .ok_or_else(|| Error::ValueError("length too large".to_string()))?;
+            let end = self.position() + size as u64;
+            value.clear();
+            // the elements can't read past their length
+            self.with_limit(size as u64, |ar| {
+                while ar.position() < end {
+                    let start = ar.position();
+                    let mut um = T::default();
+                    ar.archive(&mut um)?;
+                    if ar.position() == start {
+                        return Err(Error::ValueError(format!(
+                            "element at offset {} is empty, so the length can't be reached",
+                            start
+                        )));
+                    }
+                    value.push(um);
+                }
+                Ok(())
+            })
+        } else {
+            // encode the elements up front to learn their size, where they will be
+            // written: after the length
+            let len_size = encode_scratch(self, self.position(), |ar| {
+                L::archive_len(ar, 0).map(|_| ())
+            })?
+            .len();
+            let start = self.position() + len_size as u64;
+            let bytes = encode_scratch(self, start, |ar| ar.archive_vec(value.len(), value))?;
+            if bytes.len() % unit != 0 {
+                return Err(Error::ValueError(format!(
+                    "size {} is not a multiple of {} bytes",
+                    bytes.len(),
+                    unit
+                )));
+            }
+            L::archive_len(self, bytes.len() / unit)?;
+            self.write_all(&bytes)
         }
     }
 
-    fn archive_len_vec<TLen: Archivable + Into<usize> + TryFrom<usize>, T: Archivable>(
+    /// Archives a vector prefixed with a weighted element count, encoded according to
+    /// `L`. Each element counts as `weight(element)`. When reading, elements are read
+    /// until their weights add up to the count, failing if they overshoot it. `L` must
+    /// count elements, not bytes.
+    fn archive_weighted_vec<L: LenPolicy, T: Archivable>(
         &mut self,
         value: &mut Vec<T>,
+        weight: impl Fn(&T) -> usize,
     ) -> Result<()> {
-        let len = TLen::try_from(value.len());
-        if let Ok(mut len) = len {
-            self.archive(&mut len)?;
-            self.archive_vec(len.into(), value)?;
+        assert!(
+            L::UNIT_BYTES.is_none(),
+            "weighted length must count elements"
+        );
+        if Self::IS_READING {
+            let count = L::archive_len(self, 0)?;
+            let mut total = 0;
+            value.clear();
+            while total < count {
+                let mut um = T::default();
+                self.archive(&mut um)?;
+                total += weight(&um);
+                value.push(um);
+            }
+            if total != count {
+                return Err(Error::ValueError(format!(
+                    "element weights add up to {} instead of {}",
+                    total, count
+                )));
+            }
             Ok(())
         } else {
-            panic!("invalid length")
+            L::archive_len(self, value.iter().map(weight).sum())?;
+            self.archive_vec(value.len(), value)
         }
     }
 
-    fn archive_slice<T: Archivable>(&mut self, length: usize, value: &mut [T]) -> Result<()> {
-        for i in 0..length {
-            self.archive(&mut value[i])?;
+    /// Sets the byte written by `skip`. Defaults to `0`.
+    fn set_fill_byte(&mut self, fill: u8);
+    fn fill_byte(&self) -> u8;
+
+    /// Skips `length` bytes. When reading, the bytes are discarded without being
+    /// checked, by seeking if the reader was created with
+    /// `ArchiveReader::new_seekable`, or by reading through a small buffer otherwise.
+    /// When writing, the fill byte is emitted.
+    fn skip(&mut self, length: u64) -> Result<()> {
+        self.skip_bytes(length)
+    }
+
+    /// Archives `length` bytes of padding. When writing, `fill` is emitted. When
+    /// reading, the bytes are discarded, or checked against `fill` if strict padding
+    /// is enabled.
+    fn pad(&mut self, length: usize, fill: u8) -> Result<()> {
+        let mut buf = [fill; 64];
+        let mut left = length;
+        while left > 0 {
+            let chunk = &mut buf[..left.min(64)];
+            if Self::IS_READING {
+                let start = self.position();
+                self.read_exact(chunk)?;
+                self.mark_skipped(start, start + chunk.len() as u64);
+                if self.is_strict_padding() {
+                    if let Some(i) = chunk.iter().position(|&b| b != fill) {
+                        return Err(Error::ValueError(format!(
+                            "invalid padding byte {:#04x} at offset {}",
+                            chunk[i],
+                            start + i as u64
+                        )));
+                    }
+                }
+            } else {
+                self.write_all(chunk)?;
+            }
+            left -= chunk.len();
         }
         Ok(())
     }
+
+    /// Pads with zeroes until `position()` is a multiple of `alignment`.
+    fn align(&mut self, alignment: usize) -> Result<()> {
+        assert!(alignment > 0, "alignment must be positive");
+        let rem = (self.position() % alignment as u64) as usize;
+        if rem != 0 {
+            self.pad(alignment - rem, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Archives a constant byte sequence. When writing, `magic` is emitted. When
+    /// reading, the bytes are checked against `magic`, failing with `Error::BadMagic`
+    /// on mismatch.
+    fn expect_bytes(&mut self, magic: &[u8]) -> Result<()> {
+        if Self::IS_READING {
+            let offset = self.position();
+            let mut found = vec![0; magic.len()];
+            self.read_exact(&mut found)?;
+            if found != magic {
+                return Err(Error::BadMagic {
+                    expected: magic.to_vec(),
+                    found,
+                    offset,
+                });
+            }
+            Ok(())
+        } else {
+            self.write_all(magic)
+        }
+    }
+
+    /// Archives a constant value, encoded with the current endianness. See `expect_bytes`.
+    fn expect<T: Archivable + Clone>(&mut self, value: &T) -> Result<()> {
+        let bytes = encode_scratch(self, self.position(), |ar| ar.archive(&mut value.clone()))?;
+        self.expect_bytes(&bytes)
+    }
+
+    /// Archives a byte-order mark, such as TIFF's `II`/`MM` or a UTF-16 BOM. When
+    /// reading, the mark selects the endianness of the archive, failing if it matches
+    /// neither `little` nor `big`. When writing, the mark for the current endianness
+    /// is emitted. Returns the endianness in effect. Wrap in `with_endian` to limit the
+    /// detected endianness to a scope.
+    fn endian_mark(&mut self, little: &[u8], big: &[u8]) -> Result<Endian> {
+        assert_eq!(little.len(), big.len(), "marks must have the same length");
+        if Self::IS_READING {
+            let offset = self.position();
+            let mut found = vec![0; little.len()];
+            self.read_exact(&mut found)?;
+            if found == little {
+                self.set_endian(Endian::Little);
+            } else if found == big {
+                self.set_endian(Endian::Big);
+            } else {
+                return Err(Error::ValueError(format!(
+                    "invalid byte-order mark {:02x?} at offset {}, expected {:02x?} or {:02x?}",
+                    found, offset, little, big
+                )));
+            }
+        } else if self.is_little_endian() {
+            self.write_all(little)?;
+        } else {
+            self.write_all(big)?;
+        }
+        Ok(self.endian())
+    }
+
+    /// Like `endian_mark`, but remembers the endianness in `endian`. When reading, the
+    /// detected endianness is stored in it. When writing, the archive is first switched
+    /// to `endian`, so the data is written back with the byte order it was read with.
+    fn archive_endian_mark(
+        &mut self,
+        endian: &mut Endian,
+        little: &[u8],
+        big: &[u8],
+    ) -> Result<()> {
+        if !Self::IS_READING {
+            self.set_endian(*endian);
+        }
+        *endian = self.endian_mark(little, big)?;
+        Ok(())
+    }
+
+    fn archive_slice<T: Archivable>(&mut self, length: usize, value: &mut [T]) -> Result<()> {
+        T::archive_all(&mut value[..length], self)
+    }
+}
+
+/// Copies the settings and contexts of `from` into a scratch archive `to`. Call
+/// again with swapped arguments to give the contexts back.
+fn swap_settings<A: Archive, B: Archive>(from: &mut A, to: &mut B) {
+    to.set_little_endian(from.is_little_endian());
+    to.set_pointer_width(from.pointer_width());
+    to.set_strict_padding(from.is_strict_padding());
+    to.set_version(from.version());
+    to.set_fill_byte(from.fill_byte());
+    std::mem::swap(to.contexts_mut(), from.contexts_mut());
+}
+
+/// Runs `f` on a writer into memory with the same settings, limit and contexts as
+/// `ar`, and returns the written bytes. Used to learn the encoding of a value before
+/// writing it at `position`, which the scratch writer starts at so that alignment
+/// comes out the same.
+pub(crate) fn encode_scratch<Ar: Archive>(
+    ar: &mut Ar,
+    position: u64,
+    f: impl FnOnce(&mut ArchiveWriter<&mut Vec<u8>>) -> Result<()>,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut writer = ArchiveWriter::with_position(&mut bytes, position, ar.limit());
+    swap_settings(ar, &mut writer);
+    let result = f(&mut writer);
+    swap_settings(&mut writer, ar);
+    result?;
+    Ok(bytes)
+}
+
+/// Runs `f` on a reader over `bytes` with the same settings and contexts as `ar`.
+pub(crate) fn decode_scratch<Ar: Archive, T>(
+    ar: &mut Ar,
+    bytes: &[u8],
+    f: impl FnOnce(&mut ArchiveReader<&[u8]>) -> Result<T>,
+) -> Result<T> {
+    let mut reader = ArchiveReader::new(bytes);
+    swap_settings(ar, &mut reader);
+    let result = f(&mut reader);
+    swap_settings(&mut reader, ar);
+    result
 }
 
 pub trait ArchiveSeekable: Archive {
+    /// Seeks the underlying stream, returning the new position. Like `position()`,
+    /// offsets are relative to where the stream was when the archive was created, so
+    /// `SeekFrom::Start(0)` goes back there.
     fn seek(&mut self, from: std::io::SeekFrom) -> Result<u64>;
+
+    /// Archives a value of `length` bytes lazily. When reading, only its offset is
+    /// recorded and the data is skipped; see `Lazy::get`. When writing, this is the
+    /// same as `archive`.
+    fn archive_lazy<T: Archivable>(&mut self, value: &mut Lazy<T>, length: u64) -> Result<()> {
+        if Self::IS_READING {
+            let offset = self.position();
+            self.seek(SeekFrom::Start(offset + length))?;
+            *value = Lazy::pending(offset, length);
+            Ok(())
+        } else {
+            self.archive(value)
+        }
+    }
 }
@@ -0,0 +1,216 @@
+use std::any::Any;
+
+use crate::Archivable;
+
+/// A checksum over a region of bytes, see `Archive::checksummed`.
+///
+/// Implement it for custom algorithms: `update` is called with the bytes of the
+/// region in order, possibly in several pieces, and `value` gives the final result.
+pub trait Checksum: Default + 'static {
+    /// The stored checksum, archived after the region with the current endianness.
+    type Value: Archivable + Copy + PartialEq + Into<u64>;
+
+    fn update(&mut self, bytes: &[u8]);
+    fn value(&self) -> Self::Value;
+}
+
+/// A checksum being computed while a region is read or written.
+pub trait Tap {
+    fn update(&mut self, bytes: &[u8]);
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<C: Checksum> Tap for C {
+    fn update(&mut self, bytes: &[u8]) {
+        Checksum::update(self, bytes);
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Lookup table of a reflected 32-bit CRC.
+const fn crc32_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Lookup table of a non-reflected 16-bit CRC.
+const fn crc16_table(poly: u16) -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+macro_rules! crc32 {
+    ($(#[$attr:meta])* $name:ident, $poly:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name(u32);
+
+        impl $name {
+            const TABLE: [u32; 256] = crc32_table($poly);
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(0xffff_ffff)
+            }
+        }
+
+        impl Checksum for $name {
+            type Value = u32;
+
+            fn update(&mut self, bytes: &[u8]) {
+                for &b in bytes {
+                    self.0 = (self.0 >> 8) ^ Self::TABLE[((self.0 ^ b as u32) & 0xff) as usize];
+                }
+            }
+
+            fn value(&self) -> u32 {
+                !self.0
+            }
+        }
+    };
+}
+
+crc32!(
+    /// CRC-32/IEEE, as used by zlib, PNG, ZIP and Ethernet.
+    Crc32,
+    0xedb8_8320
+);
+crc32!(
+    /// CRC-32C (Castagnoli), as used by iSCSI, SCTP and ext4.
+    Crc32c,
+    0x82f6_3b78
+);
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, starting at 0xffff, not reflected.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc16Ccitt(u16);
+
+impl Crc16Ccitt {
+    const TABLE: [u16; 256] = crc16_table(0x1021);
+}
+
+impl Default for Crc16Ccitt {
+    fn default() -> Self {
+        Self(0xffff)
+    }
+}
+
+impl Checksum for Crc16Ccitt {
+    type Value = u16;
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 << 8) ^ Self::TABLE[((self.0 >> 8) as u8 ^ b) as usize];
+        }
+    }
+
+    fn value(&self) -> u16 {
+        self.0
+    }
+}
+
+/// Adler-32, as used by zlib streams.
+#[derive(Clone, Copy, Debug)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self { a: 1, b: 0 }
+    }
+}
+
+impl Checksum for Adler32 {
+    type Value = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        const MOD: u32 = 65521;
+        // the sums can't overflow within a chunk this long
+        for chunk in bytes.chunks(5552) {
+            for &x in chunk {
+                self.a += x as u32;
+                self.b += self.a;
+            }
+            self.a %= MOD;
+            self.b %= MOD;
+        }
+    }
+
+    fn value(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+macro_rules! byte_sum {
+    ($(#[$attr:meta])* $name:ident, $prim:ty) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $name($prim);
+
+        impl Checksum for $name {
+            type Value = $prim;
+
+            fn update(&mut self, bytes: &[u8]) {
+                for &b in bytes {
+                    self.0 = self.0.wrapping_add(b as $prim);
+                }
+            }
+
+            fn value(&self) -> $prim {
+                self.0
+            }
+        }
+    };
+}
+
+byte_sum!(
+    /// The sum of all bytes, wrapping at 8 bits.
+    Sum8,
+    u8
+);
+byte_sum!(
+    /// The sum of all bytes, wrapping at 16 bits.
+    Sum16,
+    u16
+);
+byte_sum!(
+    /// The sum of all bytes, wrapping at 32 bits.
+    Sum32,
+    u32
+);
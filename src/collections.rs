@@ -0,0 +1,107 @@
+//! `Archivable` implementations for the std collections. All of them are prefixed
+//! with their element count as a `u32`, like a `LenVec<u32, T>`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use crate::Archivable;
+use crate::{Archive, Error, LenPolicy, Result};
+
+fn duplicate_key() -> Error {
+    Error::ValueError("duplicate key in map or set".to_string())
+}
+
+impl<T: Archivable> Archivable for Vec<T> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive_len_vec::<u32, T>(self)
+    }
+}
+
+/// Written in key order. Duplicate keys are rejected when read.
+impl<K, V> Archivable for BTreeMap<K, V>
+where
+    K: Archivable + Ord + Clone,
+    V: Archivable,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let len = u32::archive_len(ar, self.len())?;
+        if Ar::IS_READING {
+            self.clear();
+            for _ in 0..len {
+                let mut k = K::default();
+                let mut v = V::default();
+                ar.archive(&mut k)?;
+                ar.archive(&mut v)?;
+                if self.insert(k, v).is_some() {
+                    return Err(duplicate_key());
+                }
+            }
+        } else {
+            for (k, v) in self.iter_mut() {
+                ar.archive(&mut k.clone())?;
+                ar.archive(v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Written sorted by key, so the output does not depend on the hasher. Duplicate
+/// keys are rejected when read.
+impl<K, V, S> Archivable for HashMap<K, V, S>
+where
+    K: Archivable + Ord + Hash + Clone,
+    V: Archivable,
+    S: BuildHasher + Default,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let len = u32::archive_len(ar, self.len())?;
+        if Ar::IS_READING {
+            self.clear();
+            // the count isn't trusted until the entries are actually read
+            self.reserve(len.min(4096));
+            for _ in 0..len {
+                let mut k = K::default();
+                let mut v = V::default();
+                ar.archive(&mut k)?;
+                ar.archive(&mut v)?;
+                if self.insert(k, v).is_some() {
+                    return Err(duplicate_key());
+                }
+            }
+        } else {
+            let mut keys: Vec<K> = self.keys().cloned().collect();
+            keys.sort();
+            for mut k in keys {
+                ar.archive(&mut k)?;
+                ar.archive(self.get_mut(&k).unwrap())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Written in order. Duplicate values are rejected when read.
+impl<T> Archivable for BTreeSet<T>
+where
+    T: Archivable + Ord + Clone,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let len = u32::archive_len(ar, self.len())?;
+        if Ar::IS_READING {
+            self.clear();
+            for _ in 0..len {
+                let mut v = T::default();
+                ar.archive(&mut v)?;
+                if !self.insert(v) {
+                    return Err(duplicate_key());
+                }
+            }
+        } else {
+            for v in self.iter() {
+                ar.archive(&mut v.clone())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// User contexts carried by an archive, at most one per type.
+#[derive(Default)]
+pub struct Contexts {
+    map: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Contexts {
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    pub fn insert<T: Any>(&mut self, value: T) -> Option<T> {
+        let old = self.map.insert(TypeId::of::<T>(), Box::new(value))?;
+        old.downcast().ok().map(|b| *b)
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        let old = self.map.remove(&TypeId::of::<T>())?;
+        old.downcast().ok().map(|b| *b)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::Archivable;
+use crate::{Archive, Result};
+
+/// Byte order used for multi-byte values. Defaults to `Little`, like the archives.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// The byte order of the host.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            Self::Little
+        } else {
+            Self::Big
+        }
+    }
+
+    pub fn is_little(self) -> bool {
+        self == Self::Little
+    }
+}
+
+macro_rules! fixed_endian {
+    ($(#[$attr:meta])* $name:ident = $endian:expr) => {
+        $(#[$attr])*
+        #[derive(Default, Clone, Copy, PartialEq, Eq)]
+        pub struct $name<T>
+        where
+            T: Archivable,
+        {
+            value: T,
+        }
+
+        impl<T> $name<T>
+        where
+            T: Archivable,
+        {
+            pub fn new(value: T) -> Self {
+                Self { value }
+            }
+
+            pub fn into_inner(self) -> T {
+                self.value
+            }
+        }
+
+        impl<T> Archivable for $name<T>
+        where
+            T: Archivable,
+        {
+            fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+                ar.with_endian($endian, |ar| ar.archive(&mut self.value))
+            }
+        }
+
+        impl<T> std::ops::Deref for $name<T>
+        where
+            T: Archivable,
+        {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.value
+            }
+        }
+
+        impl<T> std::ops::DerefMut for $name<T>
+        where
+            T: Archivable,
+        {
+            fn deref_mut(&mut self) -> &mut T {
+                &mut self.value
+            }
+        }
+
+        impl<T> std::fmt::Debug for $name<T>
+        where
+            T: Archivable + std::fmt::Debug,
+        {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.value.fmt(fmt)
+            }
+        }
+    };
+}
+
+fixed_endian!(
+    /// A struct representing a `T` that is always archived as big-endian, regardless
+    /// of the archive setting. It `Deref`s to `T`.
+    Be = Endian::Big
+);
+
+fixed_endian!(
+    /// A struct representing a `T` that is always archived as little-endian, regardless
+    /// of the archive setting. It `Deref`s to `T`.
+    Le = Endian::Little
+);
@@ -0,0 +1,285 @@
+use crate::Archivable;
+use crate::{Archive, Result};
+
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let man = x & 0x7f_ffff;
+
+    if exp == 0xff {
+        // infinity, or NaN with the top of the payload kept and the quiet bit set
+        let nan = if man != 0 {
+            0x0200 | (man >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // round to nearest, ties to even, dropping `shift` bits from `man`
+    let round = |man: u32, shift: u32| -> u32 {
+        let half = 1 << (shift - 1);
+        let rem = man & ((1 << shift) - 1);
+        let r = man >> shift;
+        if rem > half || (rem == half && r & 1 != 0) {
+            r + 1
+        } else {
+            r
+        }
+    };
+
+    if exp <= 0 {
+        // subnormal, or too small and rounded to zero
+        if exp < -10 {
+            return sign;
+        }
+        return sign | round(man | 0x80_0000, (14 - exp) as u32) as u16;
+    }
+
+    // a carry out of the mantissa correctly bumps the exponent, up to infinity
+    sign | round(((exp as u32) << 23) | man, 13) as u16
+}
+
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let man = (bits & 0x3ff) as u32;
+
+    match exp {
+        0 => {
+            let v = man as f32 / (1 << 24) as f32;
+            if sign != 0 {
+                -v
+            } else {
+                v
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (man << 13)),
+    }
+}
+
+fn f32_to_bf16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    if value.is_nan() {
+        return ((x >> 16) | 0x0040) as u16;
+    }
+    // round to nearest, ties to even
+    ((x + 0x7fff + ((x >> 16) & 1)) >> 16) as u16
+}
+
+fn bf16_bits_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// Converts to `f32` rounding to odd: an inexact result is truncated and gets its
+/// lowest mantissa bit set. Rounding that again to a format at least 2 bits smaller
+/// gives the same result as rounding `value` directly, without double rounding.
+fn f64_to_f32_odd(value: f64) -> f32 {
+    let nearest = value as f32;
+    if !value.is_finite() || nearest as f64 == value {
+        return nearest;
+    }
+    let mut bits = nearest.to_bits();
+    if (nearest as f64).abs() > value.abs() {
+        // rounded away from zero, possibly to infinity: step back towards zero
+        bits -= 1;
+    }
+    f32::from_bits(bits | 1)
+}
+
+macro_rules! half_float {
+    ($(#[$attr:meta])* $name:ident, $to_bits:ident, $from_bits:ident) => {
+        $(#[$attr])*
+        ///
+        /// Converts to and from `f32` and `f64`, rounding to nearest. Comparisons are done
+        /// on the converted `f32` value.
+        #[derive(Default, Clone, Copy)]
+        pub struct $name(u16);
+
+        impl $name {
+            pub fn from_bits(bits: u16) -> Self {
+                Self(bits)
+            }
+
+            pub fn to_bits(self) -> u16 {
+                self.0
+            }
+
+            pub fn to_f32(self) -> f32 {
+                $from_bits(self.0)
+            }
+
+            pub fn to_f64(self) -> f64 {
+                self.to_f32() as f64
+            }
+        }
+
+        impl From<f32> for $name {
+            fn from(value: f32) -> Self {
+                Self($to_bits(value))
+            }
+        }
+
+        impl From<f64> for $name {
+            fn from(value: f64) -> Self {
+                Self($to_bits(f64_to_f32_odd(value)))
+            }
+        }
+
+        impl From<$name> for f32 {
+            fn from(value: $name) -> f32 {
+                value.to_f32()
+            }
+        }
+
+        impl From<$name> for f64 {
+            fn from(value: $name) -> f64 {
+                value.to_f64()
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.to_f32() == other.to_f32()
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.to_f32().partial_cmp(&other.to_f32())
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.to_f32().fmt(fmt)
+            }
+        }
+
+        impl Archivable for $name {
+            fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+                ar.archive(&mut self.0)
+            }
+        }
+    };
+}
+
+half_float!(
+    /// An IEEE 754 half-precision float, stored in 2 bytes.
+    F16,
+    f32_to_f16_bits,
+    f16_bits_to_f32
+);
+
+half_float!(
+    /// A bfloat16 float (the top half of an `f32`), stored in 2 bytes.
+    BF16,
+    f32_to_bf16_bits,
+    bf16_bits_to_f32
+);
+
+/// An integer type that can back a `Fixed` number.
+pub trait FixedInt: Archivable + Copy {
+    fn to_f64(self) -> f64;
+    /// Converts a float, saturating at the bounds of the type.
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! fixed_int {
+    ($($typ: ty),+) => {
+        $(
+            impl FixedInt for $typ {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $typ
+                }
+            }
+        )+
+    };
+}
+
+fixed_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// A fixed-point number, stored as an `I` holding the value scaled by `2^FRAC_BITS`.
+/// Converting from a float rounds to nearest and saturates at the bounds of `I`.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed<I: FixedInt, const FRAC_BITS: u32>(I);
+
+/// TrueType `F2DOT14`: a signed 2.14 fixed-point number.
+pub type F2Dot14 = Fixed<i16, 14>;
+/// TrueType `Fixed`: a signed 16.16 fixed-point number.
+pub type Fixed16Dot16 = Fixed<i32, 16>;
+
+impl<I: FixedInt, const FRAC_BITS: u32> Fixed<I, FRAC_BITS> {
+    const SCALE: f64 = (1u64 << FRAC_BITS) as f64;
+
+    pub fn from_bits(bits: I) -> Self {
+        Self(bits)
+    }
+
+    pub fn to_bits(self) -> I {
+        self.0
+    }
+
+    pub fn from_f64(value: f64) -> Self {
+        Self(I::from_f64((value * Self::SCALE).round()))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0.to_f64() / Self::SCALE
+    }
+
+    pub fn from_f32(value: f32) -> Self {
+        Self::from_f64(value as f64)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> From<f32> for Fixed<I, FRAC_BITS> {
+    fn from(value: f32) -> Self {
+        Self::from_f32(value)
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> From<f64> for Fixed<I, FRAC_BITS> {
+    fn from(value: f64) -> Self {
+        Self::from_f64(value)
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> From<Fixed<I, FRAC_BITS>> for f32 {
+    fn from(value: Fixed<I, FRAC_BITS>) -> f32 {
+        value.to_f32()
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> From<Fixed<I, FRAC_BITS>> for f64 {
+    fn from(value: Fixed<I, FRAC_BITS>) -> f64 {
+        value.to_f64()
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> std::fmt::Debug for Fixed<I, FRAC_BITS> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_f64().fmt(fmt)
+    }
+}
+
+impl<I: FixedInt, const FRAC_BITS: u32> Archivable for Fixed<I, FRAC_BITS> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive(&mut self.0)
+    }
+}
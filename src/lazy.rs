@@ -0,0 +1,221 @@
+use std::any::Any;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::archive::decode_scratch;
+use crate::Archivable;
+use crate::{Archive, ArchiveInternal, ArchiveReader, ArchiveSeekable, Error, Result};
+
+enum LazyState<T> {
+    /// Not read yet, located in the source stream.
+    Pending {
+        offset: u64,
+        length: u64,
+    },
+    /// Not decoded, but the original bytes are kept for writing.
+    Raw(Vec<u8>),
+    Value(T),
+}
+
+/// A `T` that is only decoded when first accessed. Read it with
+/// `ArchiveSeekable::archive_lazy`, which records where the value is and skips it,
+/// then decode it with `get`, passing the same reader.
+///
+/// When written, a decoded value is archived as usual, while a value that was not
+/// decoded is written back as its original bytes: those loaded with `load_raw`, or
+/// copied from the reader attached to the writer as a `LazySource` context. Writing
+/// a value that was never loaded without a `LazySource` fails. Reading a `Lazy` with
+/// `Archive::archive` decodes it right away.
+pub struct Lazy<T: Archivable> {
+    state: LazyState<T>,
+}
+
+impl<T: Archivable> Lazy<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            state: LazyState::Value(value),
+        }
+    }
+
+    pub(crate) fn pending(offset: u64, length: u64) -> Self {
+        Self {
+            state: LazyState::Pending { offset, length },
+        }
+    }
+
+    /// The offset and length the value was read from, if it was not loaded yet.
+    pub fn location(&self) -> Option<(u64, u64)> {
+        match self.state {
+            LazyState::Pending { offset, length } => Some((offset, length)),
+            _ => None,
+        }
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        matches!(self.state, LazyState::Value(_))
+    }
+
+    /// Returns the value, decoding it first if needed. `ar` must be the reader the
+    /// value was read from. Its position is left unchanged.
+    pub fn get<Ar: ArchiveSeekable>(&mut self, ar: &mut Ar) -> Result<&mut T> {
+        let mut value = T::default();
+        match &self.state {
+            LazyState::Value(_) => {}
+            LazyState::Pending { offset, length } => {
+                let (offset, length) = (*offset, *length);
+                at_offset(ar, offset, |ar| {
+                    ar.with_limit(length, |ar| ar.archive(&mut value))
+                })?;
+                self.state = LazyState::Value(value);
+            }
+            LazyState::Raw(bytes) => {
+                decode_scratch(ar, bytes, |ar| ar.archive(&mut value))?;
+                self.state = LazyState::Value(value);
+            }
+        }
+        match &mut self.state {
+            LazyState::Value(value) => Ok(value),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reads the original bytes of a value that was not decoded yet, so it can be
+    /// written back unchanged. `ar` must be the reader the value was read from.
+    pub fn load_raw<Ar: ArchiveSeekable>(&mut self, ar: &mut Ar) -> Result<()> {
+        if let LazyState::Pending { offset, length } = self.state {
+            // grow in steps, so a bogus length fails when the data ends instead of
+            // allocating everything up front
+            let mut bytes = Vec::new();
+            at_offset(ar, offset, |ar| {
+                while (bytes.len() as u64) < length {
+                    let start = bytes.len();
+                    let step = (length - start as u64).min(4096) as usize;
+                    bytes.resize(start + step, 0);
+                    ar.read_exact(&mut bytes[start..])?;
+                }
+                Ok(())
+            })?;
+            self.state = LazyState::Raw(bytes);
+        }
+        Ok(())
+    }
+
+    /// Returns the value, if it was decoded.
+    pub fn value(&self) -> Option<&T> {
+        match &self.state {
+            LazyState::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Runs `f` at `offset`, then seeks back to the current position.
+fn at_offset<Ar: ArchiveSeekable, R>(
+    ar: &mut Ar,
+    offset: u64,
+    f: impl FnOnce(&mut Ar) -> Result<R>,
+) -> Result<R> {
+    let saved = ar.position();
+    ar.seek(SeekFrom::Start(offset))?;
+    let result = f(ar);
+    ar.seek(SeekFrom::Start(saved))?;
+    result
+}
+
+impl<T: Archivable> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Archivable> Archivable for Lazy<T> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        if Ar::IS_READING {
+            let mut value = T::default();
+            ar.archive(&mut value)?;
+            self.state = LazyState::Value(value);
+            return Ok(());
+        }
+        match &mut self.state {
+            LazyState::Value(value) => ar.archive(value),
+            LazyState::Raw(bytes) => ar.write_all(bytes),
+            LazyState::Pending { offset, length } => {
+                let (offset, length) = (*offset, *length);
+                let mut source = ar.take_context::<LazySource>().ok_or_else(|| {
+                    Error::ValueError(format!(
+                        "lazy value at offset {} was never loaded and the writer has no LazySource",
+                        offset
+                    ))
+                })?;
+                let result = source
+                    .0
+                    .copy(offset, length, &mut |bytes| ar.write_all(bytes));
+                ar.set_context(source);
+                result
+            }
+        }
+    }
+}
+
+/// The reader `Lazy` values were read from, attached to a writer with
+/// `Archive::set_context` so values that were never loaded are copied from it when
+/// written, without being decoded or held in memory.
+pub struct LazySource(Box<dyn CopyRange>);
+
+impl LazySource {
+    pub fn new<R: Read + Seek + 'static>(reader: ArchiveReader<R>) -> Self {
+        Self(Box::new(reader))
+    }
+
+    /// Returns the reader, or `None` if it is not an `ArchiveReader<R>`.
+    pub fn into_inner<R: Read + Seek + 'static>(self) -> Option<ArchiveReader<R>> {
+        self.0.into_any().downcast().ok().map(|reader| *reader)
+    }
+}
+
+trait CopyRange {
+    /// Passes the `length` bytes at `offset` to `write`, in pieces.
+    fn copy(
+        &mut self,
+        offset: u64,
+        length: u64,
+        write: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()>;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<R: Read + Seek + 'static> CopyRange for ArchiveReader<R> {
+    fn copy(
+        &mut self,
+        offset: u64,
+        length: u64,
+        write: &mut dyn FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let mut buf = [0u8; 4096];
+        at_offset(self, offset, |ar| {
+            let mut left = length;
+            while left > 0 {
+                let n = left.min(buf.len() as u64) as usize;
+                ar.read_exact(&mut buf[..n])?;
+                write(&buf[..n])?;
+                left -= n as u64;
+            }
+            Ok(())
+        })
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl<T: Archivable + std::fmt::Debug> std::fmt::Debug for Lazy<T> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.state {
+            LazyState::Pending { offset, length } => {
+                write!(fmt, "Lazy(<{} bytes at {}>)", length, offset)
+            }
+            LazyState::Raw(bytes) => write!(fmt, "Lazy(<{} raw bytes>)", bytes.len()),
+            LazyState::Value(value) => value.fmt(fmt),
+        }
+    }
+}
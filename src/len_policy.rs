@@ -0,0 +1,94 @@
+use std::marker::PhantomData;
+
+use crate::{Archive, Error, Result, U24};
+
+/// Describes how the length prefix of a `LenVec` is encoded. Implemented by the
+/// unsigned integer types, which store the element count as-is, and by the `Biased`
+/// and `Scaled` adapters, which can be nested, e.g. `Biased<ByteLen<u16>, 2>`.
+pub trait LenPolicy {
+    /// Size in bytes of one length unit, or `None` if the length counts elements.
+    const UNIT_BYTES: Option<usize> = None;
+
+    /// Archives a length, in units. When writing, `len` is encoded. When reading,
+    /// `len` is ignored and the decoded length is returned.
+    fn archive_len<Ar: Archive>(ar: &mut Ar, len: usize) -> Result<usize>;
+}
+
+macro_rules! int_len_policy {
+    ($($typ: ty),+) => {
+        $(
+            impl LenPolicy for $typ {
+                fn archive_len<Ar: Archive>(ar: &mut Ar, len: usize) -> Result<usize> {
+                    let mut stored = if Ar::IS_READING {
+                        <$typ>::default()
+                    } else {
+                        <$typ>::try_from(len).map_err(|_| {
+                            Error::ValueError(format!(
+                                "length {} does not fit in {}",
+                                len,
+                                stringify!($typ)
+                            ))
+                        })?
+                    };
+                    ar.archive(&mut stored)?;
+                    usize::try_from(stored).map_err(|_| {
+                        Error::ValueError(format!("length {:?} does not fit in usize", stored))
+                    })
+                }
+            }
+        )+
+    };
+}
+
+int_len_policy!(u8, u16, u32, u64, usize);
+
+impl LenPolicy for U24 {
+    fn archive_len<Ar: Archive>(ar: &mut Ar, len: usize) -> Result<usize> {
+        // out-of-range values are rejected when `U24` is written
+        let mut stored = U24::new(u32::try_from(len).unwrap_or(u32::MAX));
+        ar.archive(&mut stored)?;
+        Ok(stored.get() as usize)
+    }
+}
+
+/// A length stored with `BIAS` added to it, e.g. the class file's
+/// `constant_pool_count`, which is one more than the number of entries. Stored
+/// values below `BIAS` are rejected when read.
+pub struct Biased<L: LenPolicy, const BIAS: usize> {
+    pd: PhantomData<L>,
+}
+
+impl<L: LenPolicy, const BIAS: usize> LenPolicy for Biased<L, BIAS> {
+    const UNIT_BYTES: Option<usize> = L::UNIT_BYTES;
+
+    fn archive_len<Ar: Archive>(ar: &mut Ar, len: usize) -> Result<usize> {
+        let stored = len
+            .checked_add(BIAS)
+            .ok_or_else(|| Error::ValueError(format!("length {} too large", len)))?;
+        let stored = L::archive_len(ar, stored)?;
+        stored.checked_sub(BIAS).ok_or_else(|| {
+            Error::ValueError(format!("biased length {} below bias {}", stored, BIAS))
+        })
+    }
+}
+
+/// A length counting the encoded size of the elements in units of `SCALE` bytes,
+/// rather than the number of elements. When read, the elements must end exactly
+/// at the stated size.
+pub struct Scaled<L: LenPolicy, const SCALE: usize> {
+    pd: PhantomData<L>,
+}
+
+/// A length counting the encoded size of the elements in bytes.
+pub type ByteLen<L> = Scaled<L, 1>;
+
+impl<L: LenPolicy, const SCALE: usize> LenPolicy for Scaled<L, SCALE> {
+    const UNIT_BYTES: Option<usize> = match L::UNIT_BYTES {
+        Some(unit) => Some(unit * SCALE),
+        None => Some(SCALE),
+    };
+
+    fn archive_len<Ar: Archive>(ar: &mut Ar, len: usize) -> Result<usize> {
+        L::archive_len(ar, len)
+    }
+}
@@ -1,13 +1,12 @@
-use crate::Archivable;
 use crate::Error;
 use crate::LenVec;
+use crate::{Archivable, LenPolicy};
 use crate::{Archive, Result};
 use std::marker::PhantomData;
 
-#[derive(Default)]
 pub struct LenStringUtf8<TLen>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
 {
     pd: PhantomData<TLen>,
     string: String,
@@ -15,7 +14,7 @@ where
 
 impl<TLen> LenStringUtf8<TLen>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
 {
     fn encode(&self) -> Vec<u8> {
         self.string.clone().into_bytes()
@@ -23,14 +22,26 @@ where
 
     fn decode(&mut self, vec: Vec<u8>) -> Result<()> {
         self.string = String::from_utf8(vec)
-            .or_else(|e| Err(Error::ValueError(format!("invalid utf-8: {}", e))))?;
+            .map_err(|e| Error::ValueError(format!("invalid utf-8: {}", e)))?;
         Ok(())
     }
 }
 
+impl<TLen> Default for LenStringUtf8<TLen>
+where
+    TLen: LenPolicy,
+{
+    fn default() -> Self {
+        Self {
+            pd: PhantomData,
+            string: String::new(),
+        }
+    }
+}
+
 impl<TLen> Archivable for LenStringUtf8<TLen>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
 {
     fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
         let mut lv = LenVec::<TLen, _>::new(if Ar::IS_READING {
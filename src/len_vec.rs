@@ -1,14 +1,15 @@
 use std::marker::PhantomData;
 
-use crate::Archivable;
+use crate::{Archivable, LenPolicy};
 use crate::{Archive, Result};
 
 /// A struct representing a vector of `T`, which is always length-prefixed with `TLen`
-/// when written to or read from files. It `Deref`s to a `Vec<T>`.
-#[derive(Default, PartialEq, Eq)]
+/// when written to or read from files. `TLen` is either an unsigned integer holding
+/// the element count, or a `LenPolicy` such as `Biased` or `ByteLen`. It `Deref`s to
+/// a `Vec<T>`.
 pub struct LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable,
 {
     pd: PhantomData<TLen>,
@@ -17,7 +18,7 @@ where
 
 impl<TLen, T> LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable,
 {
     pub fn new(vec: Vec<T>) -> Self {
@@ -28,9 +29,36 @@ where
     }
 }
 
+impl<TLen, T> Default for LenVec<TLen, T>
+where
+    TLen: LenPolicy,
+    T: Archivable,
+{
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl<TLen, T> PartialEq for LenVec<TLen, T>
+where
+    TLen: LenPolicy,
+    T: Archivable + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.vec == other.vec
+    }
+}
+
+impl<TLen, T> Eq for LenVec<TLen, T>
+where
+    TLen: LenPolicy,
+    T: Archivable + Eq,
+{
+}
+
 impl<TLen, T> Archivable for LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable,
 {
     fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
@@ -40,7 +68,7 @@ where
 
 impl<TLen, T> std::ops::Deref for LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable,
 {
     type Target = Vec<T>;
@@ -52,7 +80,7 @@ where
 
 impl<TLen, T> std::ops::DerefMut for LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable,
 {
     fn deref_mut(&mut self) -> &mut Vec<T> {
@@ -62,7 +90,7 @@ where
 
 impl<TLen, T> std::fmt::Debug for LenVec<TLen, T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
+    TLen: LenPolicy,
     T: Archivable + std::fmt::Debug,
 {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -70,12 +98,12 @@ where
     }
 }
 
-impl<TLen, T> Into<Vec<T>> for LenVec<TLen, T>
+impl<TLen, T> From<LenVec<TLen, T>> for Vec<T>
 where
-    TLen: Archivable + TryFrom<usize> + Into<usize>,
-    T: Archivable + std::fmt::Debug,
+    TLen: LenPolicy,
+    T: Archivable,
 {
-    fn into(self) -> Vec<T> {
-        self.vec
+    fn from(value: LenVec<TLen, T>) -> Vec<T> {
+        value.vec
     }
 }
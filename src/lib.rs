@@ -56,20 +56,51 @@
 #[macro_use]
 mod macros;
 
+mod aligned;
 mod archivable;
 mod archive;
+mod checksum;
+mod collections;
+mod context;
+mod endian;
+mod float;
+mod lazy;
+mod len_policy;
 mod len_string;
 mod len_vec;
+mod magic;
+mod odd_int;
+mod parser;
+mod presence;
 mod reader;
 mod result;
+mod terminated;
+mod transform;
+mod updater;
 mod writer;
 
+pub use aligned::{Aligned, Reserved};
 pub use archivable::Archivable;
 pub(crate) use archive::ArchiveInternal;
-pub use archive::{Archive, ArchiveSeekable};
+pub use archive::{Archive, ArchiveSeekable, PointerWidth};
+pub use checksum::{Adler32, Checksum, Crc16Ccitt, Crc32, Crc32c, Sum16, Sum32, Sum8};
+pub use endian::{Be, Endian, Le};
+pub use float::{F2Dot14, Fixed, Fixed16Dot16, FixedInt, BF16, F16};
+pub use lazy::{Lazy, LazySource};
+pub use len_policy::{Biased, ByteLen, LenPolicy, Scaled};
+pub use len_string::LenStringUtf8;
 pub use len_vec::LenVec;
-pub use reader::ArchiveReader;
+pub use magic::{Magic, MagicBytes};
+pub use odd_int::{I24, I40, I48, I56, U24, U40, U48, U56};
+pub use parser::{Parsed, PushBuffer};
+pub use presence::PresenceFields;
+pub use reader::{ArchiveReader, Records, DEFAULT_BUFFER_CAPACITY};
 pub use result::{Error, Result};
+pub use terminated::{DefaultTerminator, Sentinel, Terminated, Terminator, UntilEof};
+#[cfg(feature = "deflate")]
+pub use transform::{Deflate, Zlib};
+pub use transform::{RollingXor, Transform, Xor};
+pub use updater::ArchiveUpdater;
 pub use writer::ArchiveWriter;
 
 #[cfg(test)]
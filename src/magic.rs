@@ -0,0 +1,38 @@
+use std::marker::PhantomData;
+
+use crate::{Archivable, Archive, Result};
+
+/// A constant byte sequence, used as the parameter of `Magic`.
+pub trait MagicBytes {
+    const BYTES: &'static [u8];
+}
+
+/// A struct representing a constant field, such as a file signature. It holds no
+/// data: `M::BYTES` are written as-is, and checked when read (see `Archive::expect_bytes`).
+pub struct Magic<M: MagicBytes> {
+    pd: PhantomData<M>,
+}
+
+impl<M: MagicBytes> Magic<M> {
+    pub fn bytes(&self) -> &'static [u8] {
+        M::BYTES
+    }
+}
+
+impl<M: MagicBytes> Default for Magic<M> {
+    fn default() -> Self {
+        Self { pd: PhantomData }
+    }
+}
+
+impl<M: MagicBytes> Archivable for Magic<M> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.expect_bytes(M::BYTES)
+    }
+}
+
+impl<M: MagicBytes> std::fmt::Debug for Magic<M> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "Magic({:02x?})", M::BYTES)
+    }
+}
@@ -0,0 +1,109 @@
+use crate::Archivable;
+use crate::{Archive, Error, Result};
+
+macro_rules! odd_int {
+    ($(#[$attr:meta])* $name:ident($prim:ty, $bytes:literal)) => {
+        $(#[$attr])*
+        ///
+        /// Any value of the underlying primitive can be stored, but writing a value that
+        /// does not fit fails with `Error::ValueError`.
+        #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name($prim);
+
+        impl $name {
+            const SHIFT: u32 = <$prim>::BITS - $bytes * 8;
+            pub const MIN: $prim = <$prim>::MIN >> Self::SHIFT;
+            pub const MAX: $prim = <$prim>::MAX >> Self::SHIFT;
+
+            pub fn new(value: $prim) -> Self {
+                Self(value)
+            }
+
+            pub fn get(self) -> $prim {
+                self.0
+            }
+
+            pub fn fits(self) -> bool {
+                (Self::MIN..=Self::MAX).contains(&self.0)
+            }
+        }
+
+        impl From<$prim> for $name {
+            fn from(value: $prim) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for $prim {
+            fn from(value: $name) -> $prim {
+                value.0
+            }
+        }
+
+        impl Archivable for $name {
+            fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+                const SIZE: usize = std::mem::size_of::<$prim>();
+                let mut buf = [0u8; SIZE];
+                if Ar::IS_READING {
+                    ar.read_exact(&mut buf[..$bytes])?;
+                    // move the value into the top bytes, so shifting back sign-extends it
+                    let value = if ar.is_little_endian() {
+                        <$prim>::from_le_bytes(buf) << Self::SHIFT
+                    } else {
+                        <$prim>::from_be_bytes(buf)
+                    };
+                    self.0 = value >> Self::SHIFT;
+                } else {
+                    if !self.fits() {
+                        return Err(Error::ValueError(format!(
+                            "value {} out of range for {}",
+                            self.0,
+                            stringify!($name)
+                        )));
+                    }
+                    if ar.is_little_endian() {
+                        buf = self.0.to_le_bytes();
+                        ar.write_all(&buf[..$bytes])?;
+                    } else {
+                        buf = self.0.to_be_bytes();
+                        ar.write_all(&buf[SIZE - $bytes..])?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+odd_int!(
+    /// An unsigned 24-bit integer, stored in 3 bytes.
+    U24(u32, 3)
+);
+odd_int!(
+    /// A signed 24-bit integer, stored in 3 bytes.
+    I24(i32, 3)
+);
+odd_int!(
+    /// An unsigned 40-bit integer, stored in 5 bytes.
+    U40(u64, 5)
+);
+odd_int!(
+    /// A signed 40-bit integer, stored in 5 bytes.
+    I40(i64, 5)
+);
+odd_int!(
+    /// An unsigned 48-bit integer, stored in 6 bytes.
+    U48(u64, 6)
+);
+odd_int!(
+    /// A signed 48-bit integer, stored in 6 bytes.
+    I48(i64, 6)
+);
+odd_int!(
+    /// An unsigned 56-bit integer, stored in 7 bytes.
+    U56(u64, 7)
+);
+odd_int!(
+    /// A signed 56-bit integer, stored in 7 bytes.
+    I56(i64, 7)
+);
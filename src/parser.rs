@@ -0,0 +1,89 @@
+use std::io::{ErrorKind, Read};
+
+use crate::Archivable;
+use crate::{Archive, ArchiveReader, Error, Result};
+
+/// A `Read` source that is fed bytes as they arrive, e.g. from a socket. Use it
+/// through `ArchiveReader::push`.
+#[derive(Default)]
+pub struct PushBuffer {
+    data: Vec<u8>,
+    pos: usize,
+    /// Bytes missing for the last read that ran out of data.
+    missing: usize,
+}
+
+impl Read for PushBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.data.len() - self.pos);
+        if n < buf.len() {
+            self.missing = buf.len() - n;
+        }
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// The result of `ArchiveReader::parse`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Parsed<T> {
+    Done(T),
+    /// The buffered data ends inside the value. At least this many more bytes are
+    /// needed before it can be decoded.
+    NeedMore(usize),
+}
+
+impl ArchiveReader<PushBuffer> {
+    /// Creates a reader in push mode: bytes are added with `feed`, and values are
+    /// decoded with `parse` once enough data arrived.
+    pub fn push() -> Self {
+        Self::new(PushBuffer::default())
+    }
+
+    /// Appends bytes to the end of the buffered data.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.read.data.extend_from_slice(bytes);
+    }
+
+    /// The number of buffered bytes not consumed yet.
+    pub fn buffered(&self) -> usize {
+        self.read.data.len() - self.read.pos + self.unread_lookahead()
+    }
+
+    /// Tries to decode a `T` from the buffered data. If the data ends inside it,
+    /// returns `Parsed::NeedMore` without consuming anything, so the call can be
+    /// retried after more bytes are fed. Settings changed while decoding (such as the
+    /// endianness) are rolled back as well, but user contexts are not.
+    pub fn parse<T: Archivable>(&mut self) -> Result<Parsed<T>> {
+        // bytes left over from `peek` were taken from the end of the consumed data,
+        // so handing them back is just a rewind
+        self.read.pos -= self.drop_lookahead();
+        let pos = self.read.pos;
+        let snapshot = self.snapshot();
+        self.read.missing = 0;
+
+        // the data is in memory already, and reading ahead would skew `missing`
+        let capacity = self.buffer_capacity();
+        self.set_buffer_capacity(0);
+        let mut value = T::default();
+        let result = self.archive(&mut value);
+        self.set_buffer_capacity(capacity);
+        match result {
+            Ok(()) => {
+                // drop the consumed bytes
+                self.read.data.drain(..self.read.pos);
+                self.read.pos = 0;
+                Ok(Parsed::Done(value))
+            }
+            Err(Error::IoError(e))
+                if e.kind() == ErrorKind::UnexpectedEof && self.read.missing > 0 =>
+            {
+                self.read.pos = pos;
+                self.restore(snapshot);
+                Ok(Parsed::NeedMore(self.read.missing))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
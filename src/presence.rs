@@ -0,0 +1,58 @@
+use crate::Archivable;
+use crate::{Archive, Result};
+
+/// A set of optional fields, each controlled by one bit of a flags integer. It is
+/// implemented for tuples of `(bit, &mut Option<T>)` pairs, see
+/// `Archive::archive_presence`.
+pub trait PresenceFields {
+    /// The number of fields.
+    const COUNT: u32;
+    /// The highest bit index used by the fields.
+    fn max_bit(&self) -> u32;
+    /// The bits used by the fields.
+    fn mask(&self) -> u64;
+    /// The bits of the fields that are `Some`.
+    fn present(&self) -> u64;
+    /// Archives the fields whose bit is set in `flags`.
+    fn archive_fields<Ar: Archive>(&mut self, ar: &mut Ar, flags: u64) -> Result<()>;
+}
+
+macro_rules! presence_fields {
+    ($($tfs:ident $vs:ident),+) => {
+        impl<'a, $($tfs: Archivable),+> PresenceFields for ($((u32, &'a mut Option<$tfs>),)+) {
+            const COUNT: u32 = [$(stringify!($tfs)),+].len() as u32;
+
+            fn max_bit(&self) -> u32 {
+                let ($($vs,)+) = self;
+                0 $(.max($vs.0))+
+            }
+
+            fn mask(&self) -> u64 {
+                let ($($vs,)+) = self;
+                0 $(| 1 << $vs.0)+
+            }
+
+            fn present(&self) -> u64 {
+                let ($($vs,)+) = self;
+                0 $(| if $vs.1.is_some() { 1 << $vs.0 } else { 0 })+
+            }
+
+            fn archive_fields<Ar: Archive>(&mut self, ar: &mut Ar, flags: u64) -> Result<()> {
+                let ($($vs,)+) = self;
+                $(
+                    ar.archive_option(flags & 1 << $vs.0 != 0, $vs.1)?;
+                )+
+                Ok(())
+            }
+        }
+    };
+}
+
+presence_fields!(T0 v0);
+presence_fields!(T0 v0, T1 v1);
+presence_fields!(T0 v0, T1 v1, T2 v2);
+presence_fields!(T0 v0, T1 v1, T2 v2, T3 v3);
+presence_fields!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4);
+presence_fields!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5);
+presence_fields!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6);
+presence_fields!(T0 v0, T1 v1, T2 v2, T3 v3, T4 v4, T5 v5, T6 v6, T7 v7);
@@ -1,11 +1,56 @@
+use crate::checksum::Tap;
+use crate::context::Contexts;
 use crate::result::io_error;
-use crate::{Archivable, Archive, ArchiveInternal, ArchiveSeekable, Result};
-use std::io::{Read, Seek};
+use crate::terminated::read_until_eof;
+use crate::transform::Layer;
+use crate::{Archivable, Archive, ArchiveInternal, ArchiveSeekable, Error, PointerWidth, Result};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Block size used by `ArchiveReader::new_buffered` and `ArchiveWriter::new_buffered`.
+pub const DEFAULT_BUFFER_CAPACITY: usize = 8192;
 
 /// A reader part of the Archive mechanism.
 pub struct ArchiveReader<R: Read> {
-    read: R,
+    pub(crate) read: R,
+    little_endian: bool,
+    pointer_width: PointerWidth,
+    position: u64,
+    strict_padding: bool,
+    limit: Option<u64>,
+    version: u32,
+    contexts: Contexts,
+    /// Bytes taken from `read` but not consumed yet, starting at `lookahead_pos`.
+    lookahead: Vec<u8>,
+    lookahead_pos: usize,
+    max_lookahead: usize,
+    /// `lookahead_pos` at the start of the outermost `peek`.
+    peek_start: Option<usize>,
+    taps: Vec<Box<dyn Tap>>,
+    layers: Vec<Layer>,
+    /// Taps started before the outermost `peek`, which must not see peeked bytes.
+    peek_taps: usize,
+    fill_byte: u8,
+    /// How many bytes to read ahead from `read` at once, 0 for no buffering.
+    buffer_capacity: usize,
+    /// Set by `new_seekable`, lets `skip` seek instead of reading.
+    seek_fn: Option<fn(&mut R, SeekFrom) -> std::io::Result<u64>>,
+    /// Ranges read without being decoded, only recorded for `ArchiveUpdater`.
+    skipped: Option<Vec<Range<u64>>>,
+    /// Stream offset of position 0, found on the first `seek`.
+    base: Option<u64>,
+}
+
+/// Reader state saved before a tentative read, see `peek` and `parse`.
+pub(crate) struct Snapshot {
+    position: u64,
+    lookahead_pos: usize,
     little_endian: bool,
+    pointer_width: PointerWidth,
+    strict_padding: bool,
+    limit: Option<u64>,
+    version: u32,
 }
 
 impl<R: Read> ArchiveReader<R> {
@@ -13,8 +58,228 @@ impl<R: Read> ArchiveReader<R> {
         Self {
             read,
             little_endian: true,
+            pointer_width: PointerWidth::default(),
+            position: 0,
+            strict_padding: false,
+            limit: None,
+            version: 0,
+            contexts: Contexts::default(),
+            lookahead: Vec::new(),
+            lookahead_pos: 0,
+            max_lookahead: 4096,
+            peek_start: None,
+            taps: Vec::new(),
+            layers: Vec::new(),
+            peek_taps: 0,
+            fill_byte: 0,
+            buffer_capacity: 0,
+            seek_fn: None,
+            skipped: None,
+            base: None,
         }
     }
+
+    /// Like `new`, but reads ahead from `read` in blocks of `DEFAULT_BUFFER_CAPACITY`
+    /// bytes, instead of issuing one read per value.
+    pub fn new_buffered(read: R) -> Self {
+        let mut ar = Self::new(read);
+        ar.buffer_capacity = DEFAULT_BUFFER_CAPACITY;
+        ar
+    }
+
+    /// Sets how many bytes are read ahead at once, 0 disabling buffering. Bytes
+    /// already buffered are still read first.
+    pub fn set_buffer_capacity(&mut self, capacity: usize) {
+        self.buffer_capacity = capacity;
+    }
+
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.read
+    }
+
+    /// Returns a mutable reference to the underlying reader. Reading from it directly
+    /// skips any bytes still buffered.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.read
+    }
+
+    /// Returns the underlying reader. Bytes buffered but not read yet are lost.
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+
+    /// Skips `length` bytes of the underlying stream, seeking if possible.
+    fn skip_stream(&mut self, length: u64) -> Result<()> {
+        if let Some(seek) = self.seek_fn {
+            // seeking past the end succeeds, so stop at the end like reading would
+            let current = seek(&mut self.read, SeekFrom::Current(0)).or_else(io_error)?;
+            let end = seek(&mut self.read, SeekFrom::End(0)).or_else(io_error)?;
+            let target = current.saturating_add(length).min(end.max(current));
+            seek(&mut self.read, SeekFrom::Start(target)).or_else(io_error)?;
+            self.position += target - current;
+            if target - current < length {
+                return io_error(ErrorKind::UnexpectedEof.into());
+            }
+            return Ok(());
+        }
+        let skipped = std::io::copy(&mut (&mut self.read).take(length), &mut std::io::sink())
+            .or_else(io_error)?;
+        self.position += skipped;
+        if skipped < length {
+            return io_error(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    /// Sets how many bytes `peek` may look ahead. Defaults to 4096.
+    pub fn set_max_lookahead(&mut self, max: usize) {
+        self.max_lookahead = max;
+    }
+
+    /// Decodes a `T` without consuming it: the next read starts at the same position.
+    /// Works on any `Read` source by buffering the peeked bytes, failing if more than
+    /// the maximum lookahead is needed.
+    pub fn peek<T: Archivable>(&mut self) -> Result<T> {
+        let snapshot = self.snapshot();
+        let outermost = self.peek_start.is_none();
+        if outermost {
+            self.peek_start = Some(self.lookahead_pos);
+            self.peek_taps = self.taps.len();
+        }
+        let mut value = T::default();
+        let result = self.archive(&mut value);
+        if outermost {
+            self.peek_start = None;
+        }
+        self.restore(snapshot);
+        result.map(|()| value)
+    }
+
+    /// The number of lookahead bytes not read yet.
+    pub(crate) fn unread_lookahead(&self) -> usize {
+        self.lookahead.len() - self.lookahead_pos
+    }
+
+    /// Drops the lookahead buffer, returning how many of its bytes were not read yet.
+    pub(crate) fn drop_lookahead(&mut self) -> usize {
+        let unread = self.unread_lookahead();
+        self.lookahead.clear();
+        self.lookahead_pos = 0;
+        unread
+    }
+
+    fn skip_bytes_unlimited(&mut self, length: u64) -> Result<()> {
+        if self.peek_start.is_some() || !self.taps.is_empty() || !self.layers.is_empty() {
+            // peeked, checksummed or transformed bytes must be seen, so read them like
+            // any other data
+            let mut buf = [0u8; 256];
+            let mut left = length;
+            while left > 0 {
+                let n = left.min(buf.len() as u64) as usize;
+                self.read_exact(&mut buf[..n])?;
+                left -= n as u64;
+            }
+            return Ok(());
+        }
+        let buffered = (length as usize).min(self.unread_lookahead());
+        self.lookahead_pos += buffered;
+        self.position += buffered as u64;
+        self.skip_stream(length - buffered as u64)
+    }
+
+    /// Passes the `filled` bytes read to the checksums, failing if `value` is not full.
+    fn end_read(&mut self, value: &[u8], filled: usize) -> Result<()> {
+        let mut from = self.layers.last().map_or(0, |layer| layer.taps);
+        if self.peek_start.is_some() {
+            from = from.max(self.peek_taps);
+        }
+        for tap in &mut self.taps[from..] {
+            tap.update(&value[..filled]);
+        }
+        if filled < value.len() {
+            return io_error(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            position: self.position,
+            lookahead_pos: self.lookahead_pos,
+            little_endian: self.little_endian,
+            pointer_width: self.pointer_width,
+            strict_padding: self.strict_padding,
+            limit: self.limit,
+            version: self.version,
+        }
+    }
+
+    /// Rewinds to a snapshot. Only valid if the bytes read since are still buffered,
+    /// i.e. while peeking, or when `R` can rewind itself like `PushBuffer`.
+    pub(crate) fn restore(&mut self, snapshot: Snapshot) {
+        self.position = snapshot.position;
+        self.lookahead_pos = snapshot.lookahead_pos;
+        self.little_endian = snapshot.little_endian;
+        self.pointer_width = snapshot.pointer_width;
+        self.strict_padding = snapshot.strict_padding;
+        self.limit = snapshot.limit;
+        self.version = snapshot.version;
+    }
+
+    /// Starts recording the ranges that are skipped or padding, see `mark_skipped`.
+    pub(crate) fn record_skipped(&mut self) {
+        self.skipped = Some(Vec::new());
+    }
+
+    /// Stops recording skipped ranges, returning those recorded.
+    pub(crate) fn take_skipped(&mut self) -> Vec<Range<u64>> {
+        self.skipped.take().unwrap_or_default()
+    }
+
+    /// Returns an iterator reading `T` records one by one, until the data ends
+    /// cleanly between two records. A truncated record is yielded as an error, after
+    /// which the iterator stops.
+    pub fn iter<T: Archivable>(&mut self) -> Records<'_, R, T> {
+        Records {
+            ar: self,
+            done: false,
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Like `new`, but lets `Archive::skip` seek over skipped data instead of reading it.
+    pub fn new_seekable(read: R) -> Self {
+        let mut ar = Self::new(read);
+        ar.seek_fn = Some(R::seek);
+        ar
+    }
+}
+
+/// An iterator over the records of an `ArchiveReader`, see `ArchiveReader::iter`.
+pub struct Records<'a, R: Read, T: Archivable> {
+    ar: &'a mut ArchiveReader<R>,
+    done: bool,
+    pd: PhantomData<T>,
+}
+
+impl<R: Read, T: Archivable> Iterator for Records<'_, R, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.done {
+            return None;
+        }
+        let result = read_until_eof(self.ar).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
 }
 
 impl<R: Read> ArchiveInternal for ArchiveReader<R> {
@@ -23,7 +288,126 @@ impl<R: Read> ArchiveInternal for ArchiveReader<R> {
     }
 
     fn read_exact(&mut self, value: &mut [u8]) -> Result<()> {
-        self.read.read_exact(value).or_else(io_error)
+        // never read past the limit, and count partially read bytes, so callers can
+        // tell a clean end of data from a truncated value
+        let available = match self.limit {
+            Some(limit) => value
+                .len()
+                .min(limit.saturating_sub(self.position) as usize),
+            None => value.len(),
+        };
+
+        if let Some(layer) = self.layers.last() {
+            // inside a transformed region, read from the decoded bytes
+            let start = (self.position as usize).min(layer.data.len());
+            let filled = available.min(layer.data.len() - start);
+            value[..filled].copy_from_slice(&layer.data[start..start + filled]);
+            self.position += filled as u64;
+            return self.end_read(value, filled);
+        }
+
+        if let Some(start) = self.peek_start {
+            if self.lookahead_pos + available - start > self.max_lookahead {
+                return Err(Error::ValueError(format!(
+                    "peek needs more than {} bytes of lookahead",
+                    self.max_lookahead
+                )));
+            }
+        }
+
+        let mut filled = 0;
+        while filled < available {
+            if self.lookahead_pos == self.lookahead.len() {
+                if self.peek_start.is_none() {
+                    self.lookahead.clear();
+                    self.lookahead_pos = 0;
+                    if self.buffer_capacity == 0 {
+                        // unbuffered: read straight into the value
+                        match self.read.read(&mut value[filled..available]) {
+                            Ok(0) => break,
+                            Ok(n) => {
+                                filled += n;
+                                self.position += n as u64;
+                            }
+                            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                            Err(e) => return io_error(e),
+                        }
+                        continue;
+                    }
+                }
+                // refill the buffer; while peeking, bytes are kept to be read again
+                let old = self.lookahead.len();
+                let want = self.buffer_capacity.max(available - filled);
+                self.lookahead.resize(old + want, 0);
+                let result = self.read.read(&mut self.lookahead[old..]);
+                self.lookahead
+                    .truncate(old + *result.as_ref().unwrap_or(&0));
+                match result {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => return io_error(e),
+                }
+            }
+            let n = (available - filled).min(self.lookahead.len() - self.lookahead_pos);
+            value[filled..filled + n]
+                .copy_from_slice(&self.lookahead[self.lookahead_pos..self.lookahead_pos + n]);
+            self.lookahead_pos += n;
+            filled += n;
+            self.position += n as u64;
+        }
+        self.end_read(value, filled)
+    }
+
+    fn skip_bytes(&mut self, length: u64) -> Result<()> {
+        let start = self.position;
+        self.mark_skipped(start, start.saturating_add(length));
+        if let Some(limit) = self.limit {
+            if self.position + length > limit {
+                let rest = limit.saturating_sub(self.position);
+                self.skip_bytes_unlimited(rest)?;
+                return io_error(ErrorKind::UnexpectedEof.into());
+            }
+        }
+        self.skip_bytes_unlimited(length)
+    }
+
+    fn contexts(&self) -> &Contexts {
+        &self.contexts
+    }
+
+    fn contexts_mut(&mut self) -> &mut Contexts {
+        &mut self.contexts
+    }
+
+    fn taps_mut(&mut self) -> &mut Vec<Box<dyn Tap>> {
+        &mut self.taps
+    }
+
+    fn push_layer(&mut self, data: Vec<u8>) {
+        let limit = Some(data.len() as u64);
+        self.layers.push(Layer {
+            data,
+            position: self.position,
+            limit: self.limit,
+            taps: self.taps.len(),
+        });
+        self.position = 0;
+        self.limit = limit;
+    }
+
+    fn pop_layer(&mut self) -> Vec<u8> {
+        let layer = self.layers.pop().unwrap();
+        self.position = layer.position;
+        self.limit = layer.limit;
+        layer.data
+    }
+
+    fn mark_skipped(&mut self, start: u64, end: u64) {
+        // positions inside a transformed region are not stream offsets
+        if let (Some(skipped), true) = (&mut self.skipped, self.layers.is_empty()) {
+            skipped.push(start..end);
+        }
     }
 }
 
@@ -41,10 +425,82 @@ impl<R: Read> Archive for ArchiveReader<R> {
     fn is_little_endian(&self) -> bool {
         self.little_endian
     }
+
+    fn set_pointer_width(&mut self, width: PointerWidth) {
+        self.pointer_width = width;
+    }
+
+    fn pointer_width(&self) -> PointerWidth {
+        self.pointer_width
+    }
+
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn set_strict_padding(&mut self, strict: bool) {
+        self.strict_padding = strict;
+    }
+
+    fn is_strict_padding(&self) -> bool {
+        self.strict_padding
+    }
+
+    fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
+    }
+
+    fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn set_fill_byte(&mut self, fill: u8) {
+        self.fill_byte = fill;
+    }
+
+    fn fill_byte(&self) -> u8 {
+        self.fill_byte
+    }
 }
 
 impl<R: Read + Seek> ArchiveSeekable for ArchiveReader<R> {
-    fn seek(&mut self, from: std::io::SeekFrom) -> Result<u64> {
-        self.read.seek(from).or_else(io_error)
+    fn seek(&mut self, from: SeekFrom) -> Result<u64> {
+        if !self.layers.is_empty() {
+            return Err(Error::ValueError(
+                "can't seek inside a transformed region".to_string(),
+            ));
+        }
+        // the stream is ahead of `position` by the unread lookahead, which is dropped
+        let unread = self.unread_lookahead() as u64;
+        let base = match self.base {
+            Some(base) => base,
+            None => {
+                let current = self.read.stream_position().or_else(io_error)?;
+                current.saturating_sub(unread + self.position)
+            }
+        };
+        self.base = Some(base);
+        self.drop_lookahead();
+        let from = match from {
+            SeekFrom::Start(offset) => SeekFrom::Start(base + offset),
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - unread as i64),
+            from => from,
+        };
+        let offset = self.read.seek(from).or_else(io_error)?;
+        self.position = offset.checked_sub(base).ok_or_else(|| {
+            Error::ValueError(format!(
+                "can't seek to stream offset {}, before the start of the archive at {}",
+                offset, base
+            ))
+        })?;
+        Ok(self.position)
     }
 }
@@ -2,9 +2,21 @@
 pub enum Error {
     IoError(std::io::Error),
     ValueError(String),
+    /// A constant field (see `Archive::expect`) did not match when read.
+    BadMagic {
+        expected: Vec<u8>,
+        found: Vec<u8>,
+        offset: u64,
+    },
+    /// The checksum stored at `offset` does not match the data it covers, see
+    /// `Archive::checksummed`.
+    ChecksumMismatch {
+        stored: u64,
+        computed: u64,
+        offset: u64,
+    },
 }
 
-#[must_use = "this `Result` may be an `Err` variant, which should be handled"]
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub(crate) fn io_error<T>(e: std::io::Error) -> Result<T> {
@@ -0,0 +1,224 @@
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+
+use crate::Archivable;
+use crate::{Archive, Error, Result};
+
+/// Selects the value ending a `Terminated` sequence.
+pub trait Terminator<T> {
+    fn is_terminator(value: &T) -> bool;
+    /// The terminator to write, or an error if `T` can't represent it.
+    fn terminator() -> Result<T>;
+}
+
+/// Terminates a sequence with `T::default()`, e.g. a `0` byte.
+pub struct DefaultTerminator;
+
+impl<T: Archivable + PartialEq> Terminator<T> for DefaultTerminator {
+    fn is_terminator(value: &T) -> bool {
+        *value == T::default()
+    }
+
+    fn terminator() -> Result<T> {
+        Ok(T::default())
+    }
+}
+
+/// Terminates a sequence of integers with `VALUE`, e.g. `Sentinel<0xFFFF>`.
+pub struct Sentinel<const VALUE: i128>;
+
+impl<T, const VALUE: i128> Terminator<T> for Sentinel<VALUE>
+where
+    T: TryFrom<i128> + PartialEq,
+{
+    fn is_terminator(value: &T) -> bool {
+        T::try_from(VALUE).is_ok_and(|s| s == *value)
+    }
+
+    fn terminator() -> Result<T> {
+        T::try_from(VALUE).map_err(|_| {
+            Error::ValueError(format!(
+                "sentinel {} is out of range for the element type",
+                VALUE
+            ))
+        })
+    }
+}
+
+/// A struct representing a vector of `T` followed by a terminator element, selected
+/// by `S`. Elements are read until the terminator, which is not stored. Writing an
+/// element that equals the terminator fails. It `Deref`s to a `Vec<T>`.
+pub struct Terminated<T, S = DefaultTerminator>
+where
+    T: Archivable,
+    S: Terminator<T>,
+{
+    pd: PhantomData<S>,
+    vec: Vec<T>,
+}
+
+impl<T, S> Terminated<T, S>
+where
+    T: Archivable,
+    S: Terminator<T>,
+{
+    pub fn new(vec: Vec<T>) -> Self {
+        Self {
+            pd: PhantomData,
+            vec,
+        }
+    }
+}
+
+impl<T, S> Archivable for Terminated<T, S>
+where
+    T: Archivable,
+    S: Terminator<T>,
+{
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        // fails for both directions, as reading would never find the terminator
+        let mut terminator = S::terminator()?;
+        if Ar::IS_READING {
+            self.vec.clear();
+            loop {
+                let mut v = T::default();
+                ar.archive(&mut v)?;
+                if S::is_terminator(&v) {
+                    return Ok(());
+                }
+                self.vec.push(v);
+            }
+        } else {
+            if self.vec.iter().any(S::is_terminator) {
+                return Err(Error::ValueError(
+                    "terminated sequence contains its terminator".to_string(),
+                ));
+            }
+            ar.archive_vec(self.vec.len(), &mut self.vec)?;
+            ar.archive(&mut terminator)
+        }
+    }
+}
+
+/// A struct representing a vector of `T` which extends to the end of the data: the
+/// end of the stream, or the limit of the enclosing sub-archive (see
+/// `Archive::with_limit`). Reading fails if the data ends inside an element. It
+/// `Deref`s to a `Vec<T>`.
+pub struct UntilEof<T: Archivable> {
+    vec: Vec<T>,
+}
+
+impl<T: Archivable> UntilEof<T> {
+    pub fn new(vec: Vec<T>) -> Self {
+        Self { vec }
+    }
+}
+
+impl<T: Archivable> Archivable for UntilEof<T> {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        if Ar::IS_READING {
+            self.vec.clear();
+            while let Some(v) = read_until_eof(ar)? {
+                self.vec.push(v);
+            }
+            Ok(())
+        } else {
+            ar.archive_vec(self.vec.len(), &mut self.vec)
+        }
+    }
+}
+
+/// Reads the next `T`, or returns `None` if the data ended cleanly before it: at the
+/// end of the stream, or at the limit of the enclosing sub-archive. Fails if the data
+/// ends inside the element.
+pub(crate) fn read_until_eof<Ar: Archive, T: Archivable>(ar: &mut Ar) -> Result<Option<T>> {
+    let start = ar.position();
+    if ar.limit().is_some_and(|limit| start >= limit) {
+        return Ok(None);
+    }
+    let mut v = T::default();
+    match ar.archive(&mut v) {
+        // an empty element would be read again and again without reaching the end
+        Ok(()) if ar.position() == start => Err(Error::ValueError(format!(
+            "element at offset {} is empty, so the end of the data can't be reached",
+            start
+        ))),
+        Ok(()) => Ok(Some(v)),
+        Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+            if ar.position() == start {
+                Ok(None)
+            } else {
+                Err(Error::ValueError(format!(
+                    "data ends inside an element starting at offset {}",
+                    start
+                )))
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+macro_rules! vec_wrapper {
+    ($name:ident<$($param:ident),+> where $($bounds:tt)+) => {
+        impl<$($param),+> Default for $name<$($param),+>
+        where
+            $($bounds)+
+        {
+            fn default() -> Self {
+                Self::new(Vec::new())
+            }
+        }
+
+        impl<$($param),+> PartialEq for $name<$($param),+>
+        where
+            T: PartialEq,
+            $($bounds)+
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.vec == other.vec
+            }
+        }
+
+        impl<$($param),+> std::ops::Deref for $name<$($param),+>
+        where
+            $($bounds)+
+        {
+            type Target = Vec<T>;
+
+            fn deref(&self) -> &Vec<T> {
+                &self.vec
+            }
+        }
+
+        impl<$($param),+> std::ops::DerefMut for $name<$($param),+>
+        where
+            $($bounds)+
+        {
+            fn deref_mut(&mut self) -> &mut Vec<T> {
+                &mut self.vec
+            }
+        }
+
+        impl<$($param),+> std::fmt::Debug for $name<$($param),+>
+        where
+            T: std::fmt::Debug,
+            $($bounds)+
+        {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.vec.fmt(fmt)
+            }
+        }
+
+        impl<$($param),+> From<$name<$($param),+>> for Vec<T>
+        where
+            $($bounds)+
+        {
+            fn from(value: $name<$($param),+>) -> Vec<T> {
+                value.vec
+            }
+        }
+    };
+}
+
+vec_wrapper!(Terminated<T, S> where T: Archivable, S: Terminator<T>);
+vec_wrapper!(UntilEof<T> where T: Archivable);
@@ -155,3 +155,1330 @@ fn test_write_len_vec() {
     assert_eq!(vec![0x0302, 0x0504], *value);
     assert_eq!(vec![2, 2, 3, 4, 5], data);
 }
+
+#[test]
+fn test_read_position() {
+    let data: &[u8] = &[0, 1, 2, 3, 4, 5, 6, 7];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i: u32 = 0;
+    let mut j: u8 = 0;
+    assert_eq!(0, ar.position());
+    ar.archive(&mut i).unwrap();
+    assert_eq!(4, ar.position());
+    ar.archive(&mut j).unwrap();
+    assert_eq!(5, ar.position());
+
+    ar.seek(std::io::SeekFrom::Start(1)).unwrap();
+    assert_eq!(1, ar.position());
+    ar.archive(&mut j).unwrap();
+    assert_eq!(2, ar.position());
+    assert_eq!(1, j);
+
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(3);
+    let mut ar = ArchiveReader::new_buffered(cursor);
+    ar.archive(&mut j).unwrap();
+    assert_eq!(1, ar.position());
+    assert_eq!(1, ar.seek(std::io::SeekFrom::Current(0)).unwrap());
+    assert_eq!(3, ar.seek(std::io::SeekFrom::End(-2)).unwrap());
+    ar.seek(std::io::SeekFrom::Start(0)).unwrap();
+    ar.archive(&mut j).unwrap();
+    assert_eq!(3, j);
+    assert!(ar.seek(std::io::SeekFrom::End(-6)).is_err());
+}
+
+#[test]
+fn test_write_position() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut i: u32 = 0x03020100;
+    ar.archive(&mut i).unwrap();
+    assert_eq!(4, ar.position());
+    ar.archive(&mut i).unwrap();
+    assert_eq!(8, ar.position());
+
+    let mut cursor = Cursor::new(vec![9; 4]);
+    cursor.set_position(2);
+    let mut ar = ArchiveWriter::new(cursor);
+    ar.archive(&mut 1_u8).unwrap();
+    assert_eq!(1, ar.seek(std::io::SeekFrom::Current(0)).unwrap());
+    ar.seek(std::io::SeekFrom::Start(0)).unwrap();
+    ar.archive(&mut 2_u8).unwrap();
+    assert_eq!(vec![9, 9, 2, 9], ar.into_inner().unwrap().into_inner());
+}
+
+#[test]
+fn test_read_aligned() {
+    let data: &[u8] = &[1, 0, 0, 0, 2, 0, 0xff, 0, 3, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i: u8 = 0;
+    let mut j = Aligned::<u16, 4>::default();
+    let mut k = Aligned::<u16, 4>::default();
+    ar.archive(&mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.archive(&mut k).unwrap();
+
+    assert_eq!(1, i);
+    assert_eq!(2, *j);
+    assert_eq!(3, *k);
+
+    ar.seek(std::io::SeekFrom::Start(6)).unwrap();
+    ar.set_strict_padding(true);
+    assert!(ar.align(4).is_err());
+}
+
+#[test]
+fn test_write_aligned() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut i: u8 = 1;
+    let mut j = Aligned::<u16, 4>::new(2);
+    ar.archive(&mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.pad(3, 0xaa).unwrap();
+    ar.align(4).unwrap();
+
+    assert_eq!(vec![1, 0, 0, 0, 2, 0, 0xaa, 0xaa, 0xaa, 0, 0, 0], data);
+}
+
+struct TestMagic;
+
+impl MagicBytes for TestMagic {
+    const BYTES: &'static [u8] = b"PA";
+}
+
+#[test]
+fn test_read_magic() {
+    let data: &[u8] = &[b'P', b'A', 0xca, 0xfe, 0xba, 0xbe];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    ar.set_little_endian(false);
+
+    let mut magic = Magic::<TestMagic>::default();
+    ar.archive(&mut magic).unwrap();
+    ar.expect(&0xcafebabe_u32).unwrap();
+
+    ar.seek(std::io::SeekFrom::Start(2)).unwrap();
+    match ar.archive(&mut magic) {
+        Err(Error::BadMagic {
+            expected,
+            found,
+            offset,
+        }) => {
+            assert_eq!(b"PA".to_vec(), expected);
+            assert_eq!(vec![0xca, 0xfe], found);
+            assert_eq!(2, offset);
+        }
+        r => panic!("expected BadMagic, got {:?}", r),
+    }
+}
+
+#[test]
+fn test_write_magic() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.set_little_endian(false);
+
+    ar.archive(&mut Magic::<TestMagic>::default()).unwrap();
+    ar.expect(&0xcafebabe_u32).unwrap();
+
+    assert_eq!(vec![b'P', b'A', 0xca, 0xfe, 0xba, 0xbe], data);
+}
+
+#[test]
+fn test_read_endian() {
+    let data: &[u8] = &[0, 1, 0, 1, 1, 0, 1, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i: u16 = 0;
+    let mut j = Le::<u16>::default();
+    let mut k = Be::<u16>::default();
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut i))
+        .unwrap();
+    assert_eq!(Endian::Little, ar.endian());
+    ar.archive(&mut j).unwrap();
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut j))
+        .unwrap();
+    ar.archive(&mut k).unwrap();
+
+    assert_eq!(1, i);
+    assert_eq!(1, *j);
+    assert_eq!(0x0100, *k);
+}
+
+#[test]
+fn test_write_endian() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut i: u16 = 1;
+    ar.with_endian(Endian::Big, |ar| {
+        ar.archive(&mut i)?;
+        ar.archive(&mut Le::new(1_u16))
+    })
+    .unwrap();
+    ar.archive(&mut Be::new(1_u16)).unwrap();
+
+    assert_eq!(vec![0, 1, 1, 0, 0, 1], data);
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct TiffLike {
+    byte_order: Endian,
+    value: u16,
+}
+
+impl Archivable for TiffLike {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.with_endian(ar.endian(), |ar| {
+            ar.archive_endian_mark(&mut self.byte_order, b"II", b"MM")?;
+            ar.archive(&mut self.value)
+        })
+    }
+}
+
+#[test]
+fn test_read_endian_mark() {
+    let data: &[u8] = &[b'M', b'M', 0, 1, b'I', b'I', b'X', b'X'];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut value = TiffLike::default();
+    ar.archive(&mut value).unwrap();
+    assert_eq!(Endian::Little, ar.endian());
+    assert_eq!(
+        TiffLike {
+            byte_order: Endian::Big,
+            value: 1
+        },
+        value
+    );
+
+    assert_eq!(Endian::Little, ar.endian_mark(b"II", b"MM").unwrap());
+    assert!(ar.endian_mark(b"II", b"MM").is_err());
+}
+
+#[test]
+fn test_write_endian_mark() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut value = TiffLike {
+        byte_order: Endian::Big,
+        value: 1,
+    };
+    ar.archive(&mut value).unwrap();
+    assert_eq!(Endian::Little, ar.endian());
+    ar.endian_mark(b"II", b"MM").unwrap();
+
+    assert_eq!(vec![b'M', b'M', 0, 1, b'I', b'I'], data);
+}
+
+#[test]
+fn test_read_odd_int() {
+    let data: &[u8] = &[1, 2, 3, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 1, 2, 3, 4, 5];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i = U24::default();
+    let mut j = I24::default();
+    let mut k = I24::default();
+    let mut l = U40::default();
+    ar.archive(&mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.with_endian(Endian::Big, |ar| {
+        ar.archive(&mut k)?;
+        ar.archive(&mut l)
+    })
+    .unwrap();
+
+    assert_eq!(0x030201, i.get());
+    assert_eq!(-2, j.get());
+    assert_eq!(-1, k.get());
+    assert_eq!(0x0102030405, l.get());
+}
+
+#[test]
+fn test_write_odd_int() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut U24::new(0x030201)).unwrap();
+    ar.archive(&mut I24::new(-2)).unwrap();
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut I48::new(-1)))
+        .unwrap();
+    assert!(ar.archive(&mut U24::new(0x01000000)).is_err());
+    assert!(ar.archive(&mut I24::new(0x800000)).is_err());
+    ar.archive(&mut LenVec::<U24, u8>::new(vec![7])).unwrap();
+
+    assert_eq!(
+        vec![1, 2, 3, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 1, 0, 0, 7],
+        data
+    );
+}
+
+#[test]
+fn test_read_usize() {
+    let data: &[u8] = &[1, 0, 0, 0, 0, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff, 3, 0, 0, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i: usize = 0;
+    let mut j: isize = 0;
+    ar.archive(&mut i).unwrap();
+    ar.set_pointer_width(PointerWidth::Bits32);
+    ar.archive(&mut j).unwrap();
+
+    assert_eq!(1, i);
+    assert_eq!(-2, j);
+
+    // the previous value does not have to fit the pointer width
+    i = usize::MAX;
+    ar.archive(&mut i).unwrap();
+    assert_eq!(3, i);
+}
+
+#[test]
+fn test_write_usize() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut 1_usize).unwrap();
+    ar.set_pointer_width(PointerWidth::Bits32);
+    ar.archive(&mut -2_isize).unwrap();
+    if usize::BITS > 32 {
+        assert!(ar.archive(&mut (u32::MAX as usize + 1)).is_err());
+    }
+
+    assert_eq!(vec![1, 0, 0, 0, 0, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff], data);
+}
+
+#[test]
+fn test_read_std() {
+    let data: &[u8] = &[
+        1, 0x41, 0, 0, 0, 1, 2, 3, 0, 0, 7, 0, 1, 0, 0, 0, 2, 2, 0, 0, 0, 1, 3, 2, 4,
+    ];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut b = false;
+    let mut c = '\0';
+    let mut a = [0_u8; 3];
+    let mut z: Option<std::num::NonZeroU16> = None;
+    let mut n: Option<std::num::NonZeroU8> = None;
+    let mut v: Vec<u8> = vec![];
+    let mut m = std::collections::BTreeMap::<u8, u8>::new();
+    ar.archive(&mut b).unwrap();
+    ar.archive(&mut c).unwrap();
+    ar.archive(&mut a).unwrap();
+    ar.archive(&mut z).unwrap();
+    ar.archive(&mut n).unwrap();
+    ar.archive(&mut Box::new(0_u8)).unwrap();
+    ar.archive(&mut v).unwrap();
+    ar.archive(&mut m).unwrap();
+
+    assert!(b);
+    assert_eq!('A', c);
+    assert_eq!([1, 2, 3], a);
+    assert_eq!(None, z);
+    assert_eq!(std::num::NonZeroU8::new(7), n);
+    assert_eq!(vec![2], v);
+    assert_eq!(vec![(1, 3), (2, 4)], m.into_iter().collect::<Vec<_>>());
+
+    let data: &[u8] = &[2, 0, 0, 0x11, 0, 2, 0, 0, 0, 1, 1];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    assert!(ar.archive(&mut b).is_err());
+    assert!(ar.archive(&mut c).is_err());
+    assert!(ar
+        .archive(&mut std::collections::BTreeSet::<u8>::new())
+        .is_err());
+
+    let data: &[u8] = &[0xff, 0xff, 0xff, 0x7f];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    assert!(ar
+        .archive(&mut std::collections::HashMap::<u64, u64>::new())
+        .is_err());
+}
+
+#[test]
+fn test_write_std() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut m = std::collections::HashMap::<u16, bool>::new();
+    m.insert(3, false);
+    m.insert(1, true);
+    m.insert(2, true);
+    ar.archive(&mut true).unwrap();
+    ar.archive(&mut 'A').unwrap();
+    ar.archive(&mut [1_u8, 2]).unwrap();
+    ar.archive(&mut std::num::NonZeroU8::new(5)).unwrap();
+    ar.archive(&mut std::num::Wrapping(6_u8)).unwrap();
+    ar.archive(&mut std::marker::PhantomData::<u32>).unwrap();
+    ar.archive(&mut m).unwrap();
+
+    assert_eq!(
+        vec![1, 0x41, 0, 0, 0, 1, 2, 5, 6, 3, 0, 0, 0, 1, 0, 1, 2, 0, 1, 3, 0, 0],
+        data
+    );
+}
+
+#[test]
+fn test_read_half_float() {
+    let data: &[u8] = &[
+        2, 0x00, 0x3c, 0x00, 0xc0, 0xff, 0x7b, 0x00, 0x7c, 0x80, 0x3f, 0x00, 0xe0, 0x80, 0x01, 0,
+        1, 0x80, 0,
+    ];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut v = LenVec::<u8, F16>::default();
+    let mut h = F16::default();
+    let mut j = F16::default();
+    let mut b = BF16::default();
+    let mut f = F2Dot14::default();
+    let mut g = Fixed::<u16, 8>::default();
+    let mut i = Fixed16Dot16::default();
+    ar.archive(&mut v).unwrap();
+    ar.archive(&mut h).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.archive(&mut b).unwrap();
+    ar.archive(&mut f).unwrap();
+    ar.archive(&mut g).unwrap();
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut i))
+        .unwrap();
+
+    assert_eq!(vec![F16::from(1.0_f32), F16::from(-2.0_f32)], *v);
+    assert_eq!(65504.0, h.to_f32());
+    assert_eq!(f32::INFINITY, j.to_f32());
+    assert_eq!(1.0, b.to_f32());
+    assert_eq!(-0.5, f.to_f64());
+    assert_eq!(1.5, g.to_f64());
+    assert_eq!(1.5, i.to_f64());
+}
+
+#[test]
+fn test_write_half_float() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut F16::from(1.0_f32)).unwrap();
+    ar.archive(&mut F16::from(100000.0_f32)).unwrap();
+    ar.archive(&mut F16::from(5.960_464_5e-8_f32)).unwrap();
+    ar.archive(&mut BF16::from(-2.0_f64)).unwrap();
+    ar.archive(&mut F2Dot14::from(-0.5_f32)).unwrap();
+    ar.with_endian(Endian::Big, |ar| {
+        ar.archive(&mut Fixed16Dot16::from(1.5_f64))
+    })
+    .unwrap();
+
+    assert_eq!(
+        vec![0x00, 0x3c, 0x00, 0x7c, 0x01, 0x00, 0x00, 0xc0, 0x00, 0xe0, 0, 1, 0x80, 0],
+        data
+    );
+
+    // rounded once from f64, not through f32
+    let x = 1.0 + 2.0_f64.powi(-11) + 2.0_f64.powi(-30);
+    assert_eq!(0x3c01, F16::from(x).to_bits());
+    let x = 1.0 + 2.0_f64.powi(-8) + 2.0_f64.powi(-30);
+    assert_eq!(0x3f81, BF16::from(x).to_bits());
+    assert_eq!(0x7c00, F16::from(1e300_f64).to_bits());
+    assert_eq!(0x0000, F16::from(1e-300_f64).to_bits());
+}
+
+#[test]
+fn test_read_len_policy() {
+    let data: &[u8] = &[3, 1, 2, 4, 1, 0, 2, 0, 1, 1, 2, 0, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i = LenVec::<Biased<u8, 1>, u8>::default();
+    let mut j = LenVec::<ByteLen<u8>, u16>::default();
+    let mut k = LenVec::<Scaled<u8, 4>, u8>::default();
+    ar.archive(&mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.archive(&mut k).unwrap();
+
+    assert_eq!(vec![1, 2], *i);
+    assert_eq!(vec![1, 2], *j);
+    assert_eq!(vec![1, 2, 0, 0], *k);
+
+    let data: &[u8] = &[0, 3, 1, 0, 2, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    assert!(ar.archive(&mut i).is_err());
+    assert!(ar.archive(&mut j).is_err());
+
+    let mut ar = ArchiveReader::new(&[1_u8, 0][..]);
+    assert!(ar
+        .archive(&mut LenVec::<ByteLen<u8>, Reserved<0>>::default())
+        .is_err());
+
+    // the elements are confined to their length
+    let mut ar = ArchiveReader::new(&[2_u8, 1, 0, 2, 0][..]);
+    let mut l = LenVec::<ByteLen<u8>, UntilEof<u16>>::default();
+    let mut m: u16 = 0;
+    ar.archive(&mut l).unwrap();
+    ar.archive(&mut m).unwrap();
+    assert_eq!(1, l.len());
+    assert_eq!(vec![1], *l[0]);
+    assert_eq!(2, m);
+}
+
+#[test]
+fn test_len_policy_offset() {
+    // the elements are encoded where they end up, after the length, so the
+    // alignment padding is counted in the length
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.archive(&mut 0xaa_u8).unwrap();
+    ar.archive(&mut LenVec::<ByteLen<u8>, Aligned<u32, 4>>::new(vec![
+        Aligned::new(7),
+    ]))
+    .unwrap();
+    assert_eq!(vec![0xaa, 6, 0, 0, 7, 0, 0, 0], data);
+
+    let mut ar = ArchiveReader::new(&data[..]);
+    let mut i = (0_u8, LenVec::<ByteLen<u8>, Aligned<u32, 4>>::default());
+    ar.archive(&mut i).unwrap();
+    assert_eq!(vec![Aligned::new(7)], *i.1);
+}
+
+#[test]
+fn test_write_len_policy() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut LenVec::<Biased<u8, 1>, u8>::new(vec![1, 2]))
+        .unwrap();
+    ar.archive(&mut LenVec::<ByteLen<u8>, u16>::new(vec![1, 2]))
+        .unwrap();
+    ar.archive(&mut LenVec::<Biased<Scaled<u8, 2>, 1>, u16>::new(vec![
+        1, 2,
+    ]))
+    .unwrap();
+    assert!(ar
+        .archive(&mut LenVec::<Scaled<u8, 4>, u8>::new(vec![1, 2]))
+        .is_err());
+
+    assert_eq!(vec![3, 1, 2, 4, 1, 0, 2, 0, 3, 1, 0, 2, 0], data);
+}
+
+#[test]
+fn test_read_terminated() {
+    let data: &[u8] = &[
+        1, 2, 0, 1, 0, 0xff, 0xff, b'h', b'i', 0, 1, 0, 2, 0, 3, 0, 4, 0,
+    ];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i = Terminated::<u8>::default();
+    let mut j = Terminated::<u16, Sentinel<0xffff>>::default();
+    let mut s = String::new();
+    let mut k = UntilEof::<u16>::default();
+    let mut l = UntilEof::<u16>::default();
+    ar.archive(&mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+    ar.archive(&mut s).unwrap();
+    ar.with_limit(4, |ar| ar.archive(&mut k)).unwrap();
+    ar.archive(&mut l).unwrap();
+
+    assert_eq!(vec![1, 2], *i);
+    assert_eq!(vec![1], *j);
+    assert_eq!("hi", s);
+    assert_eq!(vec![1, 2], *k);
+    assert_eq!(vec![3, 4], *l);
+
+    let data: &[u8] = &[1, 0, 2];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    assert!(ar.archive(&mut l).is_err());
+    assert!(ar.archive(&mut UntilEof::<Reserved<0>>::default()).is_err());
+
+    let mut ar = ArchiveReader::new(&[1_u8, 0xff, 0xff][..]);
+    assert!(ar
+        .archive(&mut Terminated::<u8, Sentinel<0xffff>>::default())
+        .is_err());
+    assert_eq!(0, ar.position());
+}
+
+#[test]
+fn test_write_terminated() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut Terminated::<u8>::new(vec![1, 2])).unwrap();
+    ar.archive(&mut Terminated::<u16, Sentinel<0xffff>>::new(vec![1]))
+        .unwrap();
+    ar.archive(&mut "hi".to_string()).unwrap();
+    ar.archive(&mut UntilEof::<u16>::new(vec![3])).unwrap();
+    assert!(ar.archive(&mut Terminated::<u8>::new(vec![1, 0])).is_err());
+    assert!(ar
+        .archive(&mut Terminated::<u8, Sentinel<0xffff>>::new(vec![1]))
+        .is_err());
+
+    assert_eq!(vec![1, 2, 0, 1, 0, 0xff, 0xff, b'h', b'i', 0, 3, 0], data);
+}
+
+#[test]
+fn test_read_weighted_vec() {
+    let data: &[u8] = &[4, 1, 2, 3, 2, 2, 2];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i: Vec<u8> = vec![];
+    let weight = |x: &u8| if *x == 2 { 2 } else { 1 };
+    ar.archive_weighted_vec::<Biased<u8, 1>, _>(&mut i, weight)
+        .unwrap();
+    assert_eq!(vec![1, 2], i);
+
+    assert!(ar.archive_weighted_vec::<u8, _>(&mut i, weight).is_err());
+}
+
+#[test]
+fn test_write_weighted_vec() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut i: Vec<u8> = vec![1, 2];
+    ar.archive_weighted_vec::<Biased<u8, 1>, _>(&mut i, |x| if *x == 2 { 2 } else { 1 })
+        .unwrap();
+
+    assert_eq!(vec![4, 1, 2], data);
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct Flagged {
+    flags: u8,
+    a: Option<u8>,
+    b: Option<u16>,
+    c: Option<u8>,
+}
+
+impl Archivable for Flagged {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive_presence(
+            &mut self.flags,
+            ((0, &mut self.a), (1, &mut self.b), (7, &mut self.c)),
+        )
+    }
+}
+
+#[test]
+fn test_read_presence() {
+    let data: &[u8] = &[0x86, 2, 0, 3];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut value = Flagged::default();
+    ar.archive(&mut value).unwrap();
+
+    assert_eq!(
+        Flagged {
+            flags: 0x86,
+            a: None,
+            b: Some(2),
+            c: Some(3)
+        },
+        value
+    );
+
+    let mut flags = 0_u64;
+    let result = ar.archive_presence(&mut flags, ((64, &mut None::<u8>),));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_write_presence() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut value = Flagged {
+        flags: 0x05,
+        a: None,
+        b: Some(2),
+        c: Some(3),
+    };
+    ar.archive(&mut value).unwrap();
+
+    let mut flags = 0_u8;
+    let result = ar.archive_presence(&mut flags, ((8, &mut Some(1_u8)),));
+    assert!(result.is_err());
+    let mut flags = 0_u64;
+    let result = ar.archive_presence(&mut flags, ((64, &mut Some(1_u8)),));
+    assert!(result.is_err());
+    let result = ar.archive_presence(&mut flags, ((0, &mut Some(1_u8)), (0, &mut None::<u8>)));
+    assert!(result.is_err());
+
+    assert_eq!(0x86, value.flags);
+    assert_eq!(vec![0x86, 2, 0, 3], data);
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct Versioned {
+    version: u8,
+    a: u8,
+    b: u16,
+}
+
+impl Archivable for Versioned {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        ar.archive(&mut self.version)?;
+        ar.set_version(self.version.into());
+        ar.archive(&mut self.a)?;
+        ar.archive_since(2, &mut self.b)
+    }
+}
+
+#[test]
+fn test_read_versioned() {
+    let data: &[u8] = &[1, 5, 2, 6, 7, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut value = Versioned {
+        b: 9,
+        ..Default::default()
+    };
+    ar.archive(&mut value).unwrap();
+    assert_eq!(
+        Versioned {
+            version: 1,
+            a: 5,
+            b: 0
+        },
+        value
+    );
+
+    ar.archive(&mut value).unwrap();
+    assert_eq!(
+        Versioned {
+            version: 2,
+            a: 6,
+            b: 7
+        },
+        value
+    );
+}
+
+#[test]
+fn test_write_versioned() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut value = Versioned {
+        version: 2,
+        a: 6,
+        b: 7,
+    };
+    ar.archive(&mut value).unwrap();
+    value.version = 1;
+    ar.archive(&mut value).unwrap();
+    assert!(!ar.since(2, |_| Ok(())).unwrap());
+
+    assert_eq!(vec![2, 6, 7, 0, 1, 6], data);
+}
+
+struct StringTable(Vec<&'static str>);
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct Named {
+    name: String,
+}
+
+impl Archivable for Named {
+    fn archive<Ar: Archive>(&mut self, ar: &mut Ar) -> Result<()> {
+        let table = ar
+            .context::<StringTable>()
+            .ok_or_else(|| Error::ValueError("missing string table".to_string()))?;
+        let mut index = table.0.iter().position(|s| *s == self.name).unwrap_or(0) as u8;
+        ar.archive(&mut index)?;
+        self.name = ar.context::<StringTable>().unwrap().0[index as usize].to_string();
+        *ar.context_mut::<u32>().unwrap() += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_read_context() {
+    let data: &[u8] = &[1, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    ar.set_context(StringTable(vec!["a", "b"]));
+    ar.set_context(0_u32);
+
+    let mut value = Named::default();
+    ar.archive(&mut value).unwrap();
+    assert_eq!("b", value.name);
+    ar.archive(&mut value).unwrap();
+    assert_eq!("a", value.name);
+    assert_eq!(Some(2), ar.take_context::<u32>());
+    assert!(ar.archive(&mut value).is_err());
+}
+
+#[test]
+fn test_write_context() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.set_context(StringTable(vec!["a", "b"]));
+    ar.set_context(0_u32);
+
+    let mut value = LenVec::<ByteLen<u8>, Named>::new(vec![Named {
+        name: "b".to_string(),
+    }]);
+    ar.archive(&mut value).unwrap();
+
+    assert_eq!(Some(&1), ar.context::<u32>());
+    assert_eq!(vec![1, 1], data);
+}
+
+#[test]
+fn test_read_lazy() {
+    let data: &[u8] = &[2, 1, 0, 3, 0, 0, 0, 9];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut i = Lazy::<u16>::default();
+    let mut j = Lazy::<u32>::default();
+    let mut k: u8 = 0;
+    let mut len: u8 = 0;
+    ar.archive(&mut len).unwrap();
+    ar.archive_lazy(&mut i, len.into()).unwrap();
+    ar.archive_lazy(&mut j, 4).unwrap();
+    ar.archive(&mut k).unwrap();
+
+    assert_eq!(Some((1, 2)), i.location());
+    assert_eq!(9, k);
+    assert_eq!(1, *i.get(&mut ar).unwrap());
+    assert!(i.is_decoded());
+    assert_eq!(8, ar.position());
+    j.load_raw(&mut ar).unwrap();
+    assert_eq!(None, j.value());
+    assert_eq!(3, *j.get(&mut ar).unwrap());
+
+    // a bogus length fails at the end of the data, without allocating it
+    ar.seek(std::io::SeekFrom::Start(7)).unwrap();
+    ar.archive_lazy(&mut j, u64::MAX / 2).unwrap();
+    assert!(j.load_raw(&mut ar).is_err());
+    assert!(!j.is_decoded());
+}
+
+#[test]
+fn test_write_lazy() {
+    let source: &[u8] = &[1, 2, 3, 4];
+    let mut reader = ArchiveReader::new(Cursor::new(source));
+    let mut raw = Lazy::<u32>::default();
+    let mut pending = Lazy::<u32>::default();
+    reader.archive_lazy(&mut raw, 4).unwrap();
+    reader.seek(std::io::SeekFrom::Start(0)).unwrap();
+    reader.archive_lazy(&mut pending, 4).unwrap();
+    raw.load_raw(&mut reader).unwrap();
+
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.archive(&mut Lazy::new(5_u16)).unwrap();
+    ar.archive(&mut raw).unwrap();
+    assert!(ar.archive(&mut pending).is_err());
+
+    assert_eq!(vec![5, 0, 1, 2, 3, 4], data);
+
+    // untouched values are copied from the reader they were read from
+    let mut reader = ArchiveReader::new_seekable(Cursor::new(vec![7, 1, 2, 3, 4, 5, 6]));
+    let mut header: u8 = 0;
+    let mut body = Lazy::<[u16; 3]>::default();
+    reader.archive(&mut header).unwrap();
+    reader.archive_lazy(&mut body, 6).unwrap();
+
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.set_context(LazySource::new(reader));
+    ar.archive(&mut (header + 1, body)).unwrap();
+    let reader = ar.take_context::<LazySource>().unwrap();
+    assert_eq!(
+        7,
+        reader.into_inner::<Cursor<Vec<u8>>>().unwrap().position()
+    );
+
+    assert_eq!(vec![8, 1, 2, 3, 4, 5, 6], data);
+}
+
+#[test]
+fn test_read_iter() {
+    let data: &[u8] = &[1, 0, 2, 0, 3];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+
+    let mut records = ar.iter::<u16>();
+    assert_eq!(1, records.next().unwrap().unwrap());
+    assert_eq!(2, records.next().unwrap().unwrap());
+    assert!(records.next().unwrap().is_err());
+    assert!(records.next().is_none());
+
+    let data: &[u8] = &[1, 0, 2, 0];
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    let values: Result<Vec<u16>> = ar.iter().collect();
+    assert_eq!(vec![1, 2], values.unwrap());
+
+    let mut ar = ArchiveReader::new(data);
+    let values: Vec<Result<Reserved<0>>> = ar.iter().collect();
+    assert!(matches!(values[..], [Err(_)]));
+}
+
+#[test]
+fn test_write_iter() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.write_iter((1..=3_u16).map(|x| x * 2)).unwrap();
+
+    assert_eq!(vec![2, 0, 4, 0, 6, 0], data);
+}
+
+#[test]
+fn test_read_push() {
+    let mut ar = ArchiveReader::push();
+
+    ar.feed(&[1]);
+    assert_eq!(Parsed::NeedMore(4), ar.parse::<Custom>().unwrap());
+    ar.feed(&[2, 3]);
+    assert_eq!(Parsed::NeedMore(2), ar.parse::<Custom>().unwrap());
+    assert_eq!(3, ar.buffered());
+    ar.feed(&[4, 5, 6]);
+    assert_eq!(
+        Parsed::Done(Custom {
+            field_1: 1,
+            field_2: 0x05040302
+        }),
+        ar.parse().unwrap()
+    );
+    assert_eq!(1, ar.buffered());
+    assert_eq!(5, ar.position());
+    assert_eq!(Parsed::Done(6_u8), ar.parse().unwrap());
+    assert_eq!(Parsed::NeedMore(1), ar.parse::<u8>().unwrap());
+
+    ar.feed(&[2]);
+    assert!(ar.parse::<bool>().is_err());
+}
+
+#[test]
+fn test_read_peek() {
+    let data: &[u8] = &[1, 2, 3, 4, 5, 6];
+    let mut ar = ArchiveReader::new(data);
+
+    assert_eq!(1, ar.peek::<u8>().unwrap());
+    assert_eq!(0x0201, ar.peek::<u16>().unwrap());
+    let mut i: u8 = 0;
+    ar.archive(&mut i).unwrap();
+    assert_eq!(1, i);
+    assert_eq!(0x05040302, ar.peek::<u32>().unwrap());
+    assert_eq!(1, ar.position());
+
+    ar.set_max_lookahead(4);
+    assert!(ar.peek::<u64>().is_err());
+    let mut j = Custom::default();
+    ar.archive(&mut j).unwrap();
+    assert_eq!(
+        Custom {
+            field_1: 2,
+            field_2: 0x06050403
+        },
+        j
+    );
+
+    let mut ar = ArchiveReader::push();
+    ar.feed(&[1, 2]);
+    assert_eq!(1, ar.peek::<u8>().unwrap());
+    assert_eq!(2, ar.buffered());
+    assert_eq!(Parsed::NeedMore(1), ar.parse::<(u8, u16)>().unwrap());
+    ar.feed(&[3]);
+    assert_eq!(Parsed::Done((1, 0x0302)), ar.parse::<(u8, u16)>().unwrap());
+}
+
+#[test]
+fn test_read_skip() {
+    let data: &[u8] = &[1, 0xff, 0xff, 2, 0xff, 3];
+    let mut ar = ArchiveReader::new_seekable(Cursor::new(data));
+
+    let mut i: (u8, Reserved<2>, u8) = Default::default();
+    ar.archive(&mut i).unwrap();
+    ar.skip(1).unwrap();
+    assert_eq!(5, ar.position());
+    assert_eq!((1, 2), (i.0, i.2));
+    assert_eq!(3, ar.peek::<u8>().unwrap());
+    assert!(ar.skip(100).is_err());
+    assert_eq!(6, ar.position());
+    assert!(ar.skip(u64::MAX).is_err());
+    assert_eq!(6, ar.position());
+
+    let mut ar = ArchiveReader::new(data);
+    assert_eq!(1, ar.peek::<u8>().unwrap());
+    ar.skip(3).unwrap();
+    assert_eq!(0xff, ar.peek::<(Reserved<1>, u8)>().unwrap().1);
+    ar.skip(1).unwrap();
+    assert_eq!(4, ar.position());
+    assert!(ar.skip(3).is_err());
+    assert_eq!(6, ar.position());
+}
+
+#[test]
+fn test_write_skip() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut Reserved::<2>).unwrap();
+    ar.set_fill_byte(0xff);
+    ar.skip(1).unwrap();
+    ar.archive(&mut Reserved::<1>).unwrap();
+
+    assert_eq!(4, ar.position());
+    assert_eq!(vec![0, 0, 0xff, 0xff], data);
+}
+
+#[test]
+fn test_read_bulk() {
+    let data: Vec<u8> = (0..5000_u32).flat_map(|x| x.to_be_bytes()).collect();
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    ar.set_endian(Endian::Big);
+
+    let mut i: Vec<u32> = vec![];
+    let mut j = [0_u32; 3];
+    ar.archive_vec(4997, &mut i).unwrap();
+    ar.archive(&mut j).unwrap();
+
+    assert_eq!((0..4997).collect::<Vec<_>>(), i);
+    assert_eq!([4997, 4998, 4999], j);
+    assert!(ar.archive_vec(1, &mut i).is_err());
+
+    let data: Vec<u8> = [-2_i64, 3].iter().flat_map(|x| x.to_le_bytes()).collect();
+    let mut ar = ArchiveReader::new(Cursor::new(data));
+    let mut k = [0_i64; 2];
+    ar.archive(&mut k).unwrap();
+    assert_eq!([-2, 3], k);
+
+    let mut ar = ArchiveReader::new(Calls(Cursor::new(vec![7_u8; 3000]), 0, 0));
+    let mut b: Vec<u8> = vec![];
+    ar.archive_vec(3000, &mut b).unwrap();
+    assert_eq!(vec![7; 3000], b);
+    assert_eq!(1, ar.get_ref().1);
+
+    // large payloads are read in a number of steps logarithmic in their size
+    let mut ar = ArchiveReader::new(Calls(Cursor::new(vec![7_u8; 1 << 20]), 0, 0));
+    ar.archive_vec(1 << 20, &mut b).unwrap();
+    assert_eq!(1 << 20, b.len());
+    assert_eq!(9, ar.get_ref().1);
+}
+
+#[test]
+fn test_write_bulk() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    let mut i: Vec<u16> = (0..3000).collect();
+    ar.archive_vec(3000, &mut i).unwrap();
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut [1.0_f32, 2.0]))
+        .unwrap();
+    let mut j = [0x0102_u16, 0x0304];
+    ar.with_endian(Endian::Big, |ar| ar.archive(&mut j))
+        .unwrap();
+    assert_eq!([0x0102, 0x0304], j);
+
+    let mut expected: Vec<u8> = (0..3000_u16).flat_map(|x| x.to_le_bytes()).collect();
+    expected.extend(1.0_f32.to_be_bytes());
+    expected.extend(2.0_f32.to_be_bytes());
+    expected.extend([1, 2, 3, 4]);
+    assert_eq!(expected, data);
+}
+
+/// Counts the read and write calls made to the wrapped stream.
+struct Calls<T>(T, usize, usize);
+
+impl<T: std::io::Read> std::io::Read for Calls<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.1 += 1;
+        self.0.read(buf)
+    }
+}
+
+impl<T: std::io::Write> std::io::Write for Calls<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.2 += 1;
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<T: std::io::Seek> std::io::Seek for Calls<T> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[test]
+fn test_read_buffered() {
+    let data: Vec<u8> = (0..40).collect();
+    let mut ar = ArchiveReader::new_buffered(Calls(Cursor::new(data), 0, 0));
+    ar.set_buffer_capacity(16);
+
+    let mut i = [0_u8; 20];
+    ar.archive_slice(2, &mut i).unwrap();
+    assert_eq!(2, ar.peek::<u8>().unwrap());
+    assert_eq!(0x0302, ar.peek::<u16>().unwrap());
+    for x in &mut i[2..] {
+        ar.archive(x).unwrap();
+    }
+    assert_eq!((0..20).collect::<Vec<_>>(), i);
+    assert_eq!(2, ar.get_ref().1);
+
+    // the stream is at 32, seeking accounts for the 12 buffered bytes
+    assert_eq!(22, ar.seek(std::io::SeekFrom::Current(2)).unwrap());
+    assert_eq!(22, ar.peek::<u8>().unwrap());
+    ar.skip(17).unwrap();
+    assert_eq!(39, ar.peek::<u8>().unwrap());
+    assert!(ar.archive(&mut 0_u16).is_err());
+    assert_eq!(40, ar.into_inner().0.position());
+}
+
+#[test]
+fn test_write_buffered() {
+    let mut ar = ArchiveWriter::new_buffered(Calls(Cursor::new(Vec::new()), 0, 0));
+    ar.set_buffer_capacity(8).unwrap();
+
+    for mut x in 0..10_u8 {
+        ar.archive(&mut x).unwrap();
+    }
+    assert_eq!(1, ar.get_ref().2);
+    assert_eq!(8, ar.get_ref().0.get_ref().len());
+
+    ar.seek(std::io::SeekFrom::Start(1)).unwrap();
+    ar.archive(&mut 0xffff_u16).unwrap();
+    ar.archive(&mut [0xaa_u8; 12]).unwrap();
+    ar.flush().unwrap();
+    let calls = ar.get_ref().2;
+    ar.archive(&mut 1_u8).unwrap();
+
+    let out = ar.into_inner().unwrap();
+    assert_eq!(4, calls);
+    let mut expected = vec![0, 0xff, 0xff, 3, 4, 5, 6, 7, 8, 9];
+    expected.splice(3.., [0xaa; 12]);
+    expected.push(1);
+    assert_eq!(expected, out.0.into_inner());
+}
+
+#[test]
+fn test_update() {
+    #[rustfmt::skip]
+    let data = vec![
+        3, 0, 0x61, 0x62, 0x63, 1, 0, 0, 0,
+        1, 0, 0x64, 2, 0, 0, 0,
+    ];
+    let mut up = ArchiveUpdater::new(Calls(Cursor::new(data), 0, 0));
+
+    let (s, i) = up
+        .update(|(s, i): &mut (LenVec<u16, u8>, u32)| {
+            s[1] = 0x78;
+            *i = 0x0301;
+        })
+        .unwrap();
+    assert_eq!((&[0x61, 0x78, 0x63][..], 0x0301), (&s[..], i));
+    assert_eq!(2, up.get_ref().2);
+    up.update(|_: &mut (LenVec<u16, u8>, u32)| {}).unwrap();
+    assert_eq!(2, up.get_ref().2);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![3, 0, 0x61, 0x78, 0x63, 1, 3, 0, 0, 1, 0, 0x64, 2, 0, 0, 0],
+        up.into_inner().0.into_inner()
+    );
+}
+
+#[test]
+fn test_update_skipped() {
+    let data = vec![1, 0xaa, 0xbb, 2, 7, 0xcc, 0xdd, 0xee, 5, 0];
+    let mut up = ArchiveUpdater::new(Cursor::new(data));
+
+    up.update(
+        |(_, _, b, _, c): &mut (u8, Reserved<2>, u8, u8, Aligned<u16, 4>)| {
+            *b = 3;
+            **c = 6;
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        vec![1, 0xaa, 0xbb, 3, 7, 0xcc, 0xdd, 0xee, 6, 0],
+        up.into_inner().into_inner()
+    );
+}
+
+#[test]
+fn test_update_offset() {
+    let data = vec![1, 0, 0, 0, 5, 0, 0, 0];
+    let mut up = ArchiveUpdater::new(Calls(Cursor::new(data), 0, 0));
+
+    up.update(|_: &mut u8| {}).unwrap();
+    let i = up.update(|i: &mut Aligned<u32, 4>| **i = 6).unwrap();
+    assert_eq!(6, *i);
+    assert_eq!(8, up.reader().position());
+    assert_eq!(1, up.get_ref().2);
+    assert_eq!(vec![1, 0, 0, 0, 6, 0, 0, 0], up.into_inner().0.into_inner());
+}
+
+#[test]
+fn test_update_length() {
+    let data = vec![1, 0, 0x61, 7, 0];
+    let mut up = ArchiveUpdater::new(Cursor::new(data.clone()));
+
+    let result = up.update(|(s, i): &mut (LenVec<u16, u8>, u16)| {
+        s.push(0x62);
+        *i = 8;
+    });
+    assert!(result.is_err());
+    assert_eq!(5, up.reader().position());
+    assert_eq!(data, up.into_inner().into_inner());
+}
+
+#[test]
+fn test_read_checksum() {
+    #[rustfmt::skip]
+    let data: &[u8] = &[
+        0, 0, 0, 1, 0x49, 0x45, 0x4e, 0x44, 7, 0x4f, 0x7e, 0xda, 0x42,
+        0, 0, 0, 1, 0x49, 0x45, 0x4e, 0x44, 8, 0x4f, 0x7e, 0xda, 0x42,
+    ];
+    let mut ar = ArchiveReader::new_seekable(Cursor::new(data));
+    ar.set_endian(Endian::Big);
+
+    let mut len = 0_u32;
+    ar.archive(&mut len).unwrap();
+    let (kind, x) = ar
+        .checksummed::<Crc32, _>(|ar| {
+            assert_eq!(7, ar.peek::<(u32, u8)>().unwrap().1);
+            let mut v = ([0_u8; 3], 0_u8);
+            ar.skip(1)?;
+            ar.archive(&mut v)?;
+            Ok(v)
+        })
+        .unwrap();
+    assert_eq!(([0x45, 0x4e, 0x44], 7), (kind, x));
+
+    ar.archive(&mut len).unwrap();
+    let result = ar.checksummed::<Crc32, _>(|ar| ar.archive(&mut [0_u8; 5]));
+    assert!(matches!(
+        result,
+        Err(Error::ChecksumMismatch {
+            stored: 0x4f7eda42,
+            offset: 22,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_write_checksum() {
+    fn check<C: Checksum>() -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        let mut ar = ArchiveWriter::new(&mut data);
+        ar.set_endian(Endian::Big);
+        ar.checksummed::<C, _>(|ar| ar.archive(&mut b"123456789".clone()))
+            .unwrap();
+        data.split_off(9)
+    }
+
+    assert_eq!(vec![0xcb, 0xf4, 0x39, 0x26], check::<Crc32>());
+    assert_eq!(vec![0xe3, 0x06, 0x92, 0x83], check::<Crc32c>());
+    assert_eq!(vec![0x29, 0xb1], check::<Crc16Ccitt>());
+    assert_eq!(vec![0x09, 0x1e, 0x01, 0xde], check::<Adler32>());
+    assert_eq!(vec![0xdd], check::<Sum8>());
+    assert_eq!(vec![0x01, 0xdd], check::<Sum16>());
+    assert_eq!(vec![0, 0, 0x01, 0xdd], check::<Sum32>());
+
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    ar.checksummed::<Sum8, _>(|ar| {
+        ar.archive(&mut 1_u8)?;
+        ar.checksummed::<Sum8, _>(|ar| ar.archive(&mut 2_u8))?;
+        ar.skip(1)
+    })
+    .unwrap();
+    assert_eq!(vec![1, 2, 2, 0, 5], data);
+}
+
+#[test]
+fn test_read_transform() {
+    // a length, a payload masked with [1, 2, 3, 4] and a trailing byte
+    let data: &[u8] = &[5, 0x49, 0x67, 0x6f, 0x68, 0x6e, 9];
+    let mut ar = ArchiveReader::new(data);
+
+    let mut len = 0_u8;
+    ar.archive(&mut len).unwrap();
+    let payload = ar
+        .with_limit(len as u64, |ar| {
+            ar.transformed(Xor::new([1, 2, 3, 4]), |ar| {
+                assert_eq!(0, ar.position());
+                let mut v = vec![];
+                ar.archive_vec(5, &mut v)?;
+                assert!(ar.archive(&mut 0_u8).is_err());
+                Ok(v)
+            })
+        })
+        .unwrap();
+    assert_eq!(b"Hello".to_vec(), payload);
+    assert_eq!(6, ar.position());
+    let mut end = 0_u8;
+    ar.archive(&mut end).unwrap();
+    assert_eq!(9, end);
+
+    // a checksummed region inside a masked one, itself covered by a checksum
+    let data: &[u8] = &[0x10, 0x03, 0x05, 0x18];
+    let mut ar = ArchiveReader::new(data);
+    let i = ar
+        .checksummed::<Sum8, _>(|ar| {
+            ar.with_limit(3, |ar| {
+                ar.transformed(RollingXor::new(0x10, 0x02), |ar| {
+                    assert_eq!(0x1100, ar.peek::<u16>()?);
+                    ar.checksummed::<Sum8, _>(|ar| {
+                        let mut i = 0_u16;
+                        ar.archive(&mut i)?;
+                        Ok(i)
+                    })
+                })
+            })
+        })
+        .unwrap();
+    assert_eq!((0x1100, 4), (i, ar.position()));
+}
+
+#[test]
+fn test_write_transform() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+
+    ar.archive(&mut 7_u8).unwrap();
+    ar.checksummed::<Sum8, _>(|ar| {
+        ar.transformed(RollingXor::new(0x10, 0x02), |ar| {
+            ar.checksummed::<Sum8, _>(|ar| ar.archive(&mut 0x1100_u16))?;
+            assert_eq!(3, ar.position());
+            Ok(())
+        })
+    })
+    .unwrap();
+    ar.transformed(Xor::new([0xff]), |ar| ar.archive(&mut 1_u8))
+        .unwrap();
+
+    assert_eq!(6, ar.position());
+    assert_eq!(vec![7, 0x10, 0x03, 0x05, 0x18, 0xfe], data);
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_deflate() {
+    let mut data = Vec::<u8>::new();
+    let mut ar = ArchiveWriter::new(&mut data);
+    let mut values: Vec<u32> = (0..1000).map(|x| x % 7).collect();
+    ar.transformed(Zlib::default(), |ar| ar.archive(&mut values))
+        .unwrap();
+    assert!(data.len() < 4004);
+
+    let mut ar = ArchiveReader::new(&data[..]);
+    let read: Vec<u32> = ar
+        .transformed(Zlib::default(), |ar| {
+            let mut v = vec![];
+            ar.archive(&mut v)?;
+            Ok(v)
+        })
+        .unwrap();
+    assert_eq!(values, read);
+    assert!(ArchiveReader::new(&data[1..])
+        .transformed(Zlib::default(), |_| Ok(()))
+        .is_err());
+}
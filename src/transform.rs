@@ -0,0 +1,136 @@
+use crate::Result;
+
+/// Converts the bytes of a region between their stored form and the form the inner
+/// values are archived in, see `Archive::transformed`. Implement it to plug in
+/// ciphers, compression or other encodings.
+pub trait Transform {
+    /// Turns the bytes written inside the region into the bytes to store.
+    fn encode(&mut self, data: Vec<u8>) -> Result<Vec<u8>>;
+    /// Turns the stored bytes back into the bytes the inner values are read from.
+    fn decode(&mut self, stored: Vec<u8>) -> Result<Vec<u8>>;
+}
+
+/// State of a transformed region while its inner values are archived.
+pub struct Layer {
+    /// The decoded bytes when reading, the bytes written so far when writing.
+    pub(crate) data: Vec<u8>,
+    /// Position and limit outside the region, restored when it ends.
+    pub(crate) position: u64,
+    pub(crate) limit: Option<u64>,
+    /// The number of checksums started outside the region, which see stored bytes.
+    pub(crate) taps: usize,
+}
+
+/// XORs the data with a repeating key, e.g. a WebSocket masking key. An empty key
+/// leaves the data unchanged.
+#[derive(Clone, Debug)]
+pub struct Xor {
+    key: Vec<u8>,
+}
+
+impl Xor {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    fn apply(&self, mut data: Vec<u8>) -> Vec<u8> {
+        for (x, k) in data.iter_mut().zip(self.key.iter().cycle()) {
+            *x ^= k;
+        }
+        data
+    }
+}
+
+impl Transform for Xor {
+    fn encode(&mut self, data: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(self.apply(data))
+    }
+
+    fn decode(&mut self, stored: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(self.apply(stored))
+    }
+}
+
+/// XORs each byte with a key that changes along the data: the byte at offset `i` is
+/// XORed with `key + i * step`, wrapping around.
+#[derive(Clone, Copy, Debug)]
+pub struct RollingXor {
+    key: u8,
+    step: u8,
+}
+
+impl RollingXor {
+    pub fn new(key: u8, step: u8) -> Self {
+        Self { key, step }
+    }
+
+    fn apply(&self, mut data: Vec<u8>) -> Vec<u8> {
+        let mut key = self.key;
+        for x in &mut data {
+            *x ^= key;
+            key = key.wrapping_add(self.step);
+        }
+        data
+    }
+}
+
+impl Transform for RollingXor {
+    fn encode(&mut self, data: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(self.apply(data))
+    }
+
+    fn decode(&mut self, stored: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(self.apply(stored))
+    }
+}
+
+#[cfg(feature = "deflate")]
+macro_rules! deflate_transform {
+    ($(#[$attr:meta])* $name:ident, $compress:ident, $decompress:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name {
+            level: u8,
+        }
+
+        impl $name {
+            /// Compresses with `level`, from 0 (no compression) to 10.
+            pub fn new(level: u8) -> Self {
+                Self { level }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new(6)
+            }
+        }
+
+        impl Transform for $name {
+            fn encode(&mut self, data: Vec<u8>) -> Result<Vec<u8>> {
+                Ok(miniz_oxide::deflate::$compress(&data, self.level))
+            }
+
+            fn decode(&mut self, stored: Vec<u8>) -> Result<Vec<u8>> {
+                miniz_oxide::inflate::$decompress(&stored).map_err(|e| {
+                    crate::Error::ValueError(format!("invalid compressed data: {}", e))
+                })
+            }
+        }
+    };
+}
+
+#[cfg(feature = "deflate")]
+deflate_transform!(
+    /// Raw deflate compression, as used by ZIP entries. Needs the `deflate` feature.
+    Deflate,
+    compress_to_vec,
+    decompress_to_vec
+);
+#[cfg(feature = "deflate")]
+deflate_transform!(
+    /// Zlib compression, as used by PNG `IDAT` chunks. Needs the `deflate` feature.
+    Zlib,
+    compress_to_vec_zlib,
+    decompress_to_vec_zlib
+);
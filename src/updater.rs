@@ -0,0 +1,106 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::archive::encode_scratch;
+use crate::result::io_error;
+use crate::{Archivable, Archive, ArchiveInternal, ArchiveReader, ArchiveSeekable, Error, Result};
+
+/// Patches values in place in a stream that can be read, written and seeked, such as
+/// a file opened for reading and writing.
+///
+/// `update` decodes a value, lets it be modified, then encodes it again and writes
+/// back only the bytes that changed, at their original offsets. Skipped bytes, such
+/// as `Reserved` fields and padding, are left as they are. Values are expected
+/// to be stored contiguously, and their encoding must keep the same length: changing
+/// the length of a variable-size field is an error, as the data after it would have to
+/// move.
+pub struct ArchiveUpdater<S: Read + Write + Seek> {
+    reader: ArchiveReader<S>,
+}
+
+impl<S: Read + Write + Seek> ArchiveUpdater<S> {
+    /// Creates an updater starting at the current position of `stream`.
+    pub fn new(stream: S) -> Self {
+        Self {
+            reader: ArchiveReader::new_seekable(stream),
+        }
+    }
+
+    /// The reader values are decoded with, e.g. to set the endianness. Values are
+    /// encoded with the settings it has when `update` is called.
+    pub fn reader(&mut self) -> &mut ArchiveReader<S> {
+        &mut self.reader
+    }
+
+    /// Decodes a `T` at the current position, passes it to `f`, and writes back the
+    /// bytes whose encoding changed. Returns the updated value, the position being
+    /// left after it. Nothing is written if the encoded length changed.
+    pub fn update<T: Archivable>(&mut self, f: impl FnOnce(&mut T)) -> Result<T> {
+        let ar = &mut self.reader;
+        let start = ar.seek(SeekFrom::Current(0))?;
+        let snapshot = ar.snapshot();
+        let mut value = T::default();
+        ar.record_skipped();
+        let result = ar.archive(&mut value);
+        let skipped = ar.take_skipped();
+        result?;
+        let end = ar.position();
+
+        let mut original = vec![0; (end - start) as usize];
+        ar.seek(SeekFrom::Start(start))?;
+        ar.read_exact(&mut original)?;
+
+        f(&mut value);
+        ar.restore(snapshot);
+        let encoded = encode_scratch(ar, start, |w| w.archive(&mut value));
+        ar.seek(SeekFrom::Start(end))?;
+        let mut encoded = encoded?;
+        if encoded.len() != original.len() {
+            return Err(Error::ValueError(format!(
+                "updated value is {} bytes long instead of {}, its size can't change in place",
+                encoded.len(),
+                original.len()
+            )));
+        }
+        // skipped and padding bytes were written as the fill byte, keep them as they are
+        for range in skipped {
+            let from = (range.start.clamp(start, end) - start) as usize;
+            let to = (range.end.clamp(start, end) - start) as usize;
+            if from < to {
+                encoded[from..to].copy_from_slice(&original[from..to]);
+            }
+        }
+
+        let mut i = 0;
+        while i < encoded.len() {
+            if encoded[i] == original[i] {
+                i += 1;
+                continue;
+            }
+            let run = encoded[i..]
+                .iter()
+                .zip(&original[i..])
+                .take_while(|(new, old)| new != old)
+                .count();
+            ar.seek(SeekFrom::Start(start + i as u64))?;
+            ar.get_mut()
+                .write_all(&encoded[i..i + run])
+                .or_else(io_error)?;
+            i += run;
+        }
+        ar.get_mut().flush().or_else(io_error)?;
+        ar.seek(SeekFrom::Start(end))?;
+        Ok(value)
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+}
@@ -1,10 +1,28 @@
+use crate::checksum::Tap;
+use crate::context::Contexts;
+use crate::reader::DEFAULT_BUFFER_CAPACITY;
 use crate::result::io_error;
-use crate::{Archivable, Archive, ArchiveInternal, ArchiveSeekable, Result};
-use std::io::{Seek, Write};
+use crate::transform::Layer;
+use crate::{Archivable, Archive, ArchiveInternal, ArchiveSeekable, Error, PointerWidth, Result};
+use std::io::{ErrorKind, Seek, SeekFrom, Write};
 
 pub struct ArchiveWriter<W: Write> {
     write: W,
     little_endian: bool,
+    pointer_width: PointerWidth,
+    position: u64,
+    strict_padding: bool,
+    limit: Option<u64>,
+    version: u32,
+    contexts: Contexts,
+    fill_byte: u8,
+    /// Bytes written but not passed to `write` yet.
+    buffer: Vec<u8>,
+    buffer_capacity: usize,
+    taps: Vec<Box<dyn Tap>>,
+    layers: Vec<Layer>,
+    /// Stream offset of position 0, found on the first `seek`.
+    base: Option<u64>,
 }
 
 impl<W: Write> ArchiveWriter<W> {
@@ -12,18 +30,162 @@ impl<W: Write> ArchiveWriter<W> {
         Self {
             write,
             little_endian: true,
+            pointer_width: PointerWidth::default(),
+            position: 0,
+            strict_padding: false,
+            limit: None,
+            version: 0,
+            contexts: Contexts::default(),
+            fill_byte: 0,
+            buffer: Vec::new(),
+            buffer_capacity: 0,
+            taps: Vec::new(),
+            layers: Vec::new(),
+            base: None,
         }
     }
+
+    /// A writer whose position starts at `position` instead of 0, for encoding data
+    /// that will be written there by another archive.
+    pub(crate) fn with_position(write: W, position: u64, limit: Option<u64>) -> Self {
+        let mut ar = Self::new(write);
+        ar.position = position;
+        ar.limit = limit;
+        ar
+    }
+
+    /// Like `new`, but collects small writes into blocks of `DEFAULT_BUFFER_CAPACITY`
+    /// bytes. Buffered bytes are only written on `flush`, `into_inner` and `seek`, so
+    /// one of them must be called once done: dropping the writer loses them.
+    pub fn new_buffered(write: W) -> Self {
+        let mut ar = Self::new(write);
+        ar.buffer_capacity = DEFAULT_BUFFER_CAPACITY;
+        ar
+    }
+
+    /// Sets how many bytes are collected before writing them, 0 disabling buffering.
+    /// Bytes already buffered are written first.
+    pub fn set_buffer_capacity(&mut self, capacity: usize) -> Result<()> {
+        if capacity < self.buffer.len() {
+            self.flush_buffer()?;
+        }
+        self.buffer_capacity = capacity;
+        Ok(())
+    }
+
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    fn flush_buffer(&mut self) -> Result<()> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let result = self.write.write_all(&buffer).or_else(io_error);
+        self.buffer = buffer;
+        self.buffer.clear();
+        result
+    }
+
+    /// Writes out the buffered bytes and flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.flush_buffer()?;
+        self.write.flush().or_else(io_error)
+    }
+
+    /// Returns a reference to the underlying writer. Buffered bytes are not in it yet.
+    pub fn get_ref(&self) -> &W {
+        &self.write
+    }
+
+    /// Returns a mutable reference to the underlying writer. Writing to it directly
+    /// while bytes are buffered puts them out of order, call `flush` first.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.write
+    }
+
+    /// Flushes the buffered bytes and returns the underlying writer.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.write)
+    }
+
+    /// Writes every `T` yielded by `iter`, without collecting them first.
+    pub fn write_iter<T: Archivable>(&mut self, iter: impl IntoIterator<Item = T>) -> Result<()> {
+        for mut v in iter {
+            self.archive(&mut v)?;
+        }
+        Ok(())
+    }
 }
 
 impl<R: Write> ArchiveInternal for ArchiveWriter<R> {
     fn write_all(&mut self, value: &[u8]) -> Result<()> {
-        self.write.write_all(value).or_else(io_error)
+        if let Some(limit) = self.limit {
+            if self.position + value.len() as u64 > limit {
+                return io_error(ErrorKind::WriteZero.into());
+            }
+        }
+        let from = if let Some(layer) = self.layers.last_mut() {
+            // inside a transformed region, collect the bytes to encode them at its end
+            layer.data.extend_from_slice(value);
+            layer.taps
+        } else {
+            if self.buffer.len() + value.len() > self.buffer_capacity {
+                self.flush_buffer()?;
+            }
+            if value.len() >= self.buffer_capacity {
+                self.write.write_all(value).or_else(io_error)?;
+            } else {
+                self.buffer.extend_from_slice(value);
+            }
+            0
+        };
+        for tap in &mut self.taps[from..] {
+            tap.update(value);
+        }
+        self.position += value.len() as u64;
+        Ok(())
     }
 
     fn read_exact(&mut self, _: &mut [u8]) -> Result<()> {
         unreachable!()
     }
+
+    fn skip_bytes(&mut self, length: u64) -> Result<()> {
+        let fill = self.fill_byte;
+        self.pad(length as usize, fill)
+    }
+
+    fn contexts(&self) -> &Contexts {
+        &self.contexts
+    }
+
+    fn contexts_mut(&mut self) -> &mut Contexts {
+        &mut self.contexts
+    }
+
+    fn taps_mut(&mut self) -> &mut Vec<Box<dyn Tap>> {
+        &mut self.taps
+    }
+
+    fn push_layer(&mut self, data: Vec<u8>) {
+        self.layers.push(Layer {
+            data,
+            position: self.position,
+            limit: self.limit,
+            taps: self.taps.len(),
+        });
+        self.position = 0;
+        self.limit = None;
+    }
+
+    fn pop_layer(&mut self) -> Vec<u8> {
+        let layer = self.layers.pop().unwrap();
+        self.position = layer.position;
+        self.limit = layer.limit;
+        layer.data
+    }
+
+    fn mark_skipped(&mut self, _: u64, _: u64) {}
 }
 
 impl<W: Write> Archive for ArchiveWriter<W> {
@@ -40,10 +202,79 @@ impl<W: Write> Archive for ArchiveWriter<W> {
     fn is_little_endian(&self) -> bool {
         self.little_endian
     }
+
+    fn set_pointer_width(&mut self, width: PointerWidth) {
+        self.pointer_width = width;
+    }
+
+    fn pointer_width(&self) -> PointerWidth {
+        self.pointer_width
+    }
+
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn set_strict_padding(&mut self, strict: bool) {
+        self.strict_padding = strict;
+    }
+
+    fn is_strict_padding(&self) -> bool {
+        self.strict_padding
+    }
+
+    fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
+    }
+
+    fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.version = version;
+    }
+
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn set_fill_byte(&mut self, fill: u8) {
+        self.fill_byte = fill;
+    }
+
+    fn fill_byte(&self) -> u8 {
+        self.fill_byte
+    }
 }
 
 impl<W: Write + Seek> ArchiveSeekable for ArchiveWriter<W> {
     fn seek(&mut self, from: std::io::SeekFrom) -> Result<u64> {
-        self.write.seek(from).or_else(io_error)
+        if !self.layers.is_empty() {
+            return Err(Error::ValueError(
+                "can't seek inside a transformed region".to_string(),
+            ));
+        }
+        self.flush_buffer()?;
+        let base = match self.base {
+            Some(base) => base,
+            None => {
+                let current = self.write.stream_position().or_else(io_error)?;
+                current.saturating_sub(self.position)
+            }
+        };
+        self.base = Some(base);
+        let from = match from {
+            SeekFrom::Start(offset) => SeekFrom::Start(base + offset),
+            from => from,
+        };
+        let offset = self.write.seek(from).or_else(io_error)?;
+        self.position = offset.checked_sub(base).ok_or_else(|| {
+            Error::ValueError(format!(
+                "can't seek to stream offset {}, before the start of the archive at {}",
+                offset, base
+            ))
+        })?;
+        Ok(self.position)
     }
 }